@@ -0,0 +1,66 @@
+//! Passes the versions of the shader compilers to the crate, they are part of the shader cache keys
+//! so upgrading a compiler doesn't keep serving the SPIR-V of the previous one
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    let lock = find_lock_file().and_then(|path| {
+        println!("cargo:rerun-if-changed={}", path.display());
+        fs::read_to_string(path).ok()
+    });
+    // optional dependencies are in the lock file even when their feature is disabled
+    let version = |name: &str| match lock.as_ref().and_then(|lock| package_version(lock, name)) {
+        Some(version) => version,
+        None => {
+            // a new key every build, stale SPIR-V is never served
+            println!(
+                "cargo:warning=The version of {} isn't in Cargo.lock, shaders cached by other builds won't be used",
+                name
+            );
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default();
+            format!("unknown-{}", time)
+        }
+    };
+
+    println!(
+        "cargo:rustc-env=HAZEL_SHADERC_VERSION={}-sys-{}",
+        version("shaderc"),
+        version("shaderc-sys")
+    );
+    println!("cargo:rustc-env=HAZEL_NAGA_VERSION={}", version("naga"));
+}
+
+/// The lock file of the workspace building hazel, which isn't always hazel's own
+fn find_lock_file() -> Option<PathBuf> {
+    let dirs = ["CARGO_MANIFEST_DIR", "OUT_DIR"]
+        .iter()
+        .filter_map(|name| env::var_os(name))
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    dirs.iter()
+        .flat_map(|dir| dir.ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| Path::exists(path))
+}
+
+fn package_version(lock: &str, name: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", name);
+    let mut lines = lock.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line == name_line {
+            let version = lines.next()?;
+            if !version.starts_with("version = ") {
+                return None;
+            }
+            return Some(version["version = ".len()..].trim_matches('"').to_string());
+        }
+    }
+    None
+}
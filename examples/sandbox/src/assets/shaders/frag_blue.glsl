@@ -2,9 +2,11 @@
 
 layout(location = 0) in vec3 v_Position;
 
+layout(set = 2, binding = 0) uniform Material { vec4 u_Color; };
+
 layout(location = 0) out vec4 color;
 
 void main()
 {
-    color = vec4(0.2, 0.3, 0.8, 1.0);
+    color = u_Color;
 }
\ No newline at end of file
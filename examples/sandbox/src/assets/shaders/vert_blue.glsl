@@ -2,13 +2,13 @@
 
 layout(location = 0) in vec3 a_Position;
 
-// uniform mat4 u_ViewProjection;
+layout(set = 0, binding = 0) uniform Scene { mat4 u_ViewProjection; };
+layout(set = 1, binding = 0) uniform Transform { mat4 u_Transform; };
 
 layout(location = 0) out vec3 v_Position;
 
 void main()
 {
     v_Position = a_Position;
-    // gl_Position = u_ViewProjection * vec4(a_Position, 1.0);	
-    gl_Position = vec4(a_Position, 1.0);	
+    gl_Position = u_ViewProjection * u_Transform * vec4(a_Position, 1.0);	
 }
\ No newline at end of file
@@ -1,19 +1,24 @@
 // #![windows_subsystem = "windows"]
 
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use hazel::{
     event::Event,
     input::VirtualKeyCode,
-    layers::{debug_text::DebugTextLayer, iced_ui::IcedUiLayer, Layer},
+    layers::{
+        debug_text::DebugTextLayer, iced_ui::IcedUiLayer, imgui::particle_emitter_editor, Layer,
+    },
     renderer::{
-        pipeline::Pipeline,
+        material::{Material, UniformValue},
+        particles::{EmitterDefinition, ParticleEmitter},
+        post_process::PostProcessStack,
         primitives::{Vertex, VertexArray, VertexPos},
-        shader::Shader,
+        shader::CompileOptions,
         RenderCommand,
     },
     run, Application, Frame, Ui,
 };
 use imgui::{im_str, Condition};
-use std::path::PathBuf;
+use std::{path::PathBuf, rc::Rc};
 
 use log::{LevelFilter, SetLoggerError};
 use log4rs::{
@@ -28,10 +33,11 @@ use log4rs::{
 use std::path::Path;
 
 struct State {
-    shader: Shader,
-    blue_shader: Shader,
-    triangle_vertex_array: VertexArray<Vertex>,
-    square_vertex_array: VertexArray<VertexPos>,
+    material: Material,
+    blue_material: Material,
+    triangle_vertex_array: VertexArray,
+    square_vertex_array: VertexArray,
+    fire: ParticleEmitter,
 }
 
 struct ExampleLayer {
@@ -66,7 +72,7 @@ impl Layer for ExampleLayer {
                 },
             ];
 
-            let indices = &[0, 1, 2];
+            let indices: &[u16] = &[0, 1, 2];
 
             VertexArray::create(&app.renderer.api.device, vertices, indices)
         };
@@ -87,35 +93,80 @@ impl Layer for ExampleLayer {
                 },
             ];
 
-            let indices = &[0, 1, 2, 2, 3, 0];
+            let indices: &[u16] = &[0, 1, 2, 2, 3, 0];
 
             VertexArray::create(&app.renderer.api.device, vertices, indices)
         };
 
-        // let shader = Shader::compile(
-        //     String::from(include_str!("assets/shaders/vert.glsl")),
-        //     String::from(include_str!("assets/shaders/frag.glsl")),
-        // )
-        // .expect("failed to compile");
-
-        // let blue_shader = Shader::compile(
-        //     String::from(include_str!("assets/shaders/vert_blue.glsl")),
-        //     String::from(include_str!("assets/shaders/frag_blue.glsl")),
-        // )
-        // .expect("failed to compile");
-
-        // self.state = Some(State {
-        //     shader,
-        //     blue_shader,
-        //     triangle_vertex_array,
-        //     square_vertex_array,
-        // })
+        let shader_cache = &app.renderer.shader_cache;
+        let shader = shader_cache
+            .load(
+                include_str!("assets/shaders/vert.glsl"),
+                include_str!("assets/shaders/frag.glsl"),
+                &CompileOptions::default(),
+            )
+            .expect("failed to compile");
+
+        let blue_shader = shader_cache
+            .load(
+                include_str!("assets/shaders/vert_blue.glsl"),
+                include_str!("assets/shaders/frag_blue.glsl"),
+                &CompileOptions::default(),
+            )
+            .expect("failed to compile");
+
+        let material = Material::new(&app.renderer.api, Rc::new(shader), &[], &[]);
+        let blue_material = Material::new(
+            &app.renderer.api,
+            Rc::new(blue_shader),
+            &[("u_Color", UniformValue::Vec4(Vec4::new(0.2, 0.3, 0.8, 1.0)))],
+            &[],
+        );
+
+        match PostProcessStack::new(&mut app.renderer.api, &app.renderer.shader_cache) {
+            Ok(stack) => app.post_process = Some(stack),
+            Err(e) => log::error!("Failed to create the post processing stack {:?}", e),
+        }
+
+        // saved from the editor, so the path is the one of the source file
+        let fire_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/assets/particles/fire.json"
+        );
+        let mut fire =
+            ParticleEmitter::from_file(&mut app.renderer.api, fire_path).unwrap_or_else(|e| {
+                log::error!("{:?}", e);
+                ParticleEmitter::new(EmitterDefinition::default())
+            });
+        fire.position = Vec3::new(1.0, -0.5, 0.6);
+
+        self.state = Some(State {
+            material,
+            blue_material,
+            triangle_vertex_array,
+            square_vertex_array,
+            fire,
+        })
     }
 
     fn on_update(&mut self, app: &mut Application) {
         if app.input_context.is_key_pressed(VirtualKeyCode::A) {
             log::debug!("A poll");
         }
+
+        if let Some(state) = self.state.as_mut() {
+            state.fire.update(app.delta_t);
+        }
+
+        // toggled with F3
+        let mouse = app.mouse_world_position.extend(0.0);
+        app.debug_draw
+            .cross(mouse, 0.1, Vec4::new(1.0, 1.0, 0.0, 1.0));
+        app.debug_draw.label(
+            mouse,
+            &format!("{:.2}, {:.2}", mouse.x(), mouse.y()),
+            Vec4::one(),
+        );
     }
 
     fn on_render(&mut self, app: &mut Application, frame: &Frame) {
@@ -125,21 +176,44 @@ impl Layer for ExampleLayer {
             return;
         };
 
-        // Clear
-        app.renderer.send(RenderCommand::Clear(frame));
+        // The scene is drawn offscreen when post processing is enabled
+        let target = app
+            .post_process
+            .as_ref()
+            .map_or_else(|| frame.into(), |stack| stack.target(frame));
 
-        // set camera pos and rot
+        // Clear
+        app.renderer.send(RenderCommand::Clear(target));
 
-        app.renderer.begin_scene();
+        let mut scene = app.renderer.begin_scene(&app.camera_controller.camera);
 
-        // Submit
+        scene.submit(
+            &state.blue_material,
+            &state.square_vertex_array,
+            Mat4::identity(),
+            target,
+        );
+        scene.submit(
+            &state.material,
+            &state.triangle_vertex_array,
+            Mat4::from_translation(Vec3::new(0.0, 0.1, 0.0)),
+            target,
+        );
 
-        app.renderer
-            .submit(&state.blue_shader, &state.square_vertex_array, frame);
-        app.renderer
-            .submit(&state.shader, &state.triangle_vertex_array, frame);
+        app.renderer.end_scene(scene);
 
-        app.renderer.end_scene();
+        // a row of quads, batched in a single draw call
+        for i in 0..5 {
+            app.renderer_2d.draw_rotated_quad(
+                Vec3::new(-1.0 + i as f32 * 0.5, -0.8, 0.5),
+                Vec2::new(0.3, 0.3),
+                i as f32 * 0.2,
+                Vec4::new(0.2 * i as f32, 0.8, 0.3, 0.75),
+            );
+        }
+        state.fire.draw(&mut app.renderer_2d);
+        app.renderer_2d
+            .end_scene(&mut app.renderer, &app.camera_controller.camera, target);
     }
 
     fn on_event(&mut self, app: &mut Application, event: &Event) {
@@ -153,12 +227,15 @@ impl Layer for ExampleLayer {
         }
     }
 
-    fn on_imgui_render(&mut self, _app: &mut Application, ui: &Ui) {
+    fn on_imgui_render(&mut self, app: &mut Application, ui: &Ui) {
         // imgui::Window::new(im_str!("Test"))
         //     .position([0.0, 0.0], Condition::FirstUseEver)
         //     .build(&ui, || {
         //         ui.text(im_str!("Hello world"));
         //     });
+        if let Some(state) = self.state.as_mut() {
+            particle_emitter_editor(ui, &mut app.renderer.api, "fire", &mut state.fire);
+        }
     }
 }
 
@@ -169,7 +246,7 @@ fn main() -> Result<(), anyhow::Error> {
         Application::new("Sandbox", Some(PathBuf::from("imgui.ini")))?;
 
     layer_stack.push_layer(Box::new(ExampleLayer::new()));
-    layer_stack.push_layer(Box::new(IcedUiLayer::new()));
+    layer_stack.push_overlay(Box::new(IcedUiLayer::new()));
     layer_stack.push_overlay(Box::new(DebugTextLayer::new()));
 
     run(app, layer_stack, event_loop);
@@ -0,0 +1,79 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Implements `VertexBufferLayout` for a `#[repr(C)]` struct
+///
+/// Every field becomes a `BufferElement`, in declaration order, using the `VertexAttribute`
+/// implementation of its type.
+/// ```rust,ignore
+///     #[repr(C)]
+///     #[derive(Copy, Clone, VertexBufferLayout)]
+///     struct Vertex {
+///         position: [f32; 3],
+///         color: [f32; 4],
+///     }
+/// ```
+#[proc_macro_derive(VertexBufferLayout)]
+pub fn derive_vertex_buffer_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let is_repr_c = input.attrs.iter().any(|attr| {
+        attr.path.is_ident("repr")
+            && attr
+                .parse_args::<syn::Ident>()
+                .map(|repr| repr == "C")
+                .unwrap_or(false)
+    });
+    if !is_repr_c {
+        return syn::Error::new_spanned(
+            name,
+            "VertexBufferLayout can only be derived for #[repr(C)] structs",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new_spanned(
+                    name,
+                    "VertexBufferLayout can only be derived for structs with named fields",
+                )
+                .to_compile_error()
+                .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "VertexBufferLayout can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let elements = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let field_name = field.ident.as_ref().unwrap().to_string();
+        quote! {
+            ::hazel::renderer::buffer::BufferElement::new(
+                <#ty as ::hazel::renderer::buffer::VertexAttribute>::DATA_TYPE,
+                #field_name,
+            )
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics ::hazel::renderer::buffer::VertexBufferLayout for #name #ty_generics #where_clause {
+            fn layout() -> ::hazel::renderer::buffer::BufferLayout {
+                ::hazel::renderer::buffer::BufferLayout::new(vec![#(#elements),*])
+            }
+        }
+    };
+
+    expanded.into()
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 a_Position;
+layout(location = 1) in vec4 a_Color;
+
+layout(set = 0, binding = 0) uniform Scene { mat4 u_ViewProjection; };
+layout(set = 1, binding = 0) uniform Transform { mat4 u_Transform; };
+
+layout(location = 0) out vec4 v_Color;
+
+void main()
+{
+    v_Color = a_Color;
+    gl_Position = u_ViewProjection * u_Transform * vec4(a_Position, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    o_Color = texture(sampler2D(t_Input, s_Input), v_TexCoord);
+}
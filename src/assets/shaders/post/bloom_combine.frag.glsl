@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material { float u_Intensity; };
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+layout(set = 2, binding = 3) uniform texture2D t_Bloom;
+layout(set = 2, binding = 4) uniform sampler s_Bloom;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    vec4 color = texture(sampler2D(t_Input, s_Input), v_TexCoord);
+    vec3 bloom = texture(sampler2D(t_Bloom, s_Bloom), v_TexCoord).rgb;
+    o_Color = vec4(color.rgb + bloom * u_Intensity, color.a);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material { float u_Threshold; };
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    vec3 color = texture(sampler2D(t_Input, s_Input), v_TexCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - u_Threshold, 0.0) / max(brightness, 0.0001);
+    o_Color = vec4(color * contribution, 1.0);
+}
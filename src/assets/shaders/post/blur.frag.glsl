@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+// u_Direction is one texel along the blur axis, scaled by the radius
+layout(set = 2, binding = 0) uniform Material { vec2 u_Direction; };
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+    vec3 color = texture(sampler2D(t_Input, s_Input), v_TexCoord).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; i++)
+    {
+        vec2 offset = u_Direction * float(i);
+        color += texture(sampler2D(t_Input, s_Input), v_TexCoord + offset).rgb * WEIGHTS[i];
+        color += texture(sampler2D(t_Input, s_Input), v_TexCoord - offset).rgb * WEIGHTS[i];
+    }
+    o_Color = vec4(color, 1.0);
+}
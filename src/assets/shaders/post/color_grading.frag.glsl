@@ -0,0 +1,43 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+// u_LutSize is the number of cells per channel, the lut is u_LutSize cells wide
+// strip of u_LutSize by u_LutSize slices with blue going from left to right
+layout(set = 2, binding = 0) uniform Material {
+    float u_Strength;
+    float u_LutSize;
+};
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+layout(set = 2, binding = 3) uniform texture2D t_Lut;
+layout(set = 2, binding = 4) uniform sampler s_Lut;
+
+layout(location = 0) out vec4 o_Color;
+
+vec3 linear_to_srgb(vec3 color)
+{
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 sample_slice(float slice, vec2 cell)
+{
+    vec2 uv = vec2((slice * u_LutSize + cell.x + 0.5) / (u_LutSize * u_LutSize), (cell.y + 0.5) / u_LutSize);
+    return texture(sampler2D(t_Lut, s_Lut), uv).rgb;
+}
+
+void main()
+{
+    vec4 color = texture(sampler2D(t_Input, s_Input), v_TexCoord);
+    // luts are authored in srgb, the lut texture decodes the result back to linear
+    vec3 srgb = linear_to_srgb(clamp(color.rgb, 0.0, 1.0)) * (u_LutSize - 1.0);
+
+    vec2 cell = srgb.rg;
+    float slice = floor(srgb.b);
+    vec3 graded = mix(
+        sample_slice(slice, cell),
+        sample_slice(min(slice + 1.0, u_LutSize - 1.0), cell),
+        srgb.b - slice
+    );
+    o_Color = vec4(mix(color.rgb, graded, u_Strength), color.a);
+}
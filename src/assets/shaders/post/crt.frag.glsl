@@ -0,0 +1,30 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material {
+    float u_Curvature;
+    float u_ScanlineIntensity;
+    float u_ScanlineCount;
+};
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    vec2 centered = v_TexCoord * 2.0 - 1.0;
+    centered *= 1.0 + u_Curvature * dot(centered.yx, centered.yx);
+    vec2 uv = centered * 0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0)
+    {
+        o_Color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(sampler2D(t_Input, s_Input), uv);
+    float scanline = 0.5 + 0.5 * sin(uv.y * u_ScanlineCount * 3.14159265);
+    o_Color = vec4(color.rgb * mix(1.0, scanline, u_ScanlineIntensity), color.a);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec3 a_Position;
+
+layout(location = 0) out vec2 v_TexCoord;
+
+void main()
+{
+    v_TexCoord = a_Position.xy * vec2(0.5, -0.5) + 0.5;
+    gl_Position = vec4(a_Position.xy, 0.0, 1.0);
+}
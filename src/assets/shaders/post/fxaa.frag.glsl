@@ -0,0 +1,56 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material { vec2 u_TexelSize; };
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 color)
+{
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+vec3 sample_offset(vec2 offset)
+{
+    return texture(sampler2D(t_Input, s_Input), v_TexCoord + offset * u_TexelSize).rgb;
+}
+
+void main()
+{
+    vec4 center = texture(sampler2D(t_Input, s_Input), v_TexCoord);
+    float luma_nw = luma(sample_offset(vec2(-1.0, -1.0)));
+    float luma_ne = luma(sample_offset(vec2(1.0, -1.0)));
+    float luma_sw = luma(sample_offset(vec2(-1.0, 1.0)));
+    float luma_se = luma(sample_offset(vec2(1.0, 1.0)));
+    float luma_m = luma(center.rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2(-SPAN_MAX), vec2(SPAN_MAX));
+
+    vec3 color_a = 0.5 * (
+        sample_offset(direction * (1.0 / 3.0 - 0.5)) +
+        sample_offset(direction * (2.0 / 3.0 - 0.5))
+    );
+    vec3 color_b = color_a * 0.5 + 0.25 * (
+        sample_offset(direction * -0.5) +
+        sample_offset(direction * 0.5)
+    );
+
+    float luma_b = luma(color_b);
+    o_Color = vec4((luma_b < luma_min || luma_b > luma_max) ? color_a : color_b, center.a);
+}
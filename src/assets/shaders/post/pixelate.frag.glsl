@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material {
+    vec2 u_Resolution;
+    float u_PixelSize;
+};
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    vec2 cells = u_Resolution / max(u_PixelSize, 1.0);
+    vec2 uv = (floor(v_TexCoord * cells) + 0.5) / cells;
+    o_Color = texture(sampler2D(t_Input, s_Input), uv);
+}
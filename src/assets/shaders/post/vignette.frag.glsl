@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 0) uniform Material {
+    float u_Intensity;
+    float u_Smoothness;
+};
+layout(set = 2, binding = 1) uniform texture2D t_Input;
+layout(set = 2, binding = 2) uniform sampler s_Input;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    vec4 color = texture(sampler2D(t_Input, s_Input), v_TexCoord);
+    float distance_to_center = length(v_TexCoord - 0.5) * 1.41421356;
+    float vignette = smoothstep(1.0, 1.0 - u_Smoothness, distance_to_center);
+    o_Color = vec4(color.rgb * mix(1.0, vignette, u_Intensity), color.a);
+}
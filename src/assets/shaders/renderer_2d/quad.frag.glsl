@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec4 v_Color;
+layout(location = 1) in vec2 v_TexCoord;
+
+layout(set = 2, binding = 1) uniform texture2D t_Texture;
+layout(set = 2, binding = 2) uniform sampler s_Texture;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    o_Color = texture(sampler2D(t_Texture, s_Texture), v_TexCoord) * v_Color;
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec3 a_Position;
+layout(location = 1) in vec4 a_Color;
+layout(location = 2) in vec2 a_TexCoord;
+layout(location = 3) in vec4 a_OutlineColor;
+layout(location = 4) in float a_OutlineWidth;
+
+layout(set = 0, binding = 0) uniform Scene { mat4 u_ViewProjection; };
+layout(set = 1, binding = 0) uniform Transform { mat4 u_Transform; };
+
+layout(location = 0) out vec4 v_Color;
+layout(location = 1) out vec2 v_TexCoord;
+layout(location = 2) out vec4 v_OutlineColor;
+layout(location = 3) out float v_OutlineWidth;
+
+void main()
+{
+    v_Color = a_Color;
+    v_TexCoord = a_TexCoord;
+    v_OutlineColor = a_OutlineColor;
+    v_OutlineWidth = a_OutlineWidth;
+    gl_Position = u_ViewProjection * u_Transform * vec4(a_Position, 1.0);
+}
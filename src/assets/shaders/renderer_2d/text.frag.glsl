@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec4 v_Color;
+layout(location = 1) in vec2 v_TexCoord;
+layout(location = 2) in vec4 v_OutlineColor;
+layout(location = 3) in float v_OutlineWidth;
+
+// signed distance field in the alpha channel, 0.5 on the edge
+layout(set = 2, binding = 1) uniform texture2D t_Texture;
+layout(set = 2, binding = 2) uniform sampler s_Texture;
+
+layout(location = 0) out vec4 o_Color;
+
+void main()
+{
+    float distance = texture(sampler2D(t_Texture, s_Texture), v_TexCoord).a;
+    // about a pixel of antialiasing at any scale
+    float smoothing = max(fwidth(distance) * 0.5, 0.0001);
+    float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, distance);
+    float edge = 0.5 - v_OutlineWidth;
+    float shape = smoothstep(edge - smoothing, edge + smoothing, distance);
+    vec4 color = mix(v_OutlineColor, v_Color, fill);
+    o_Color = vec4(color.rgb, color.a * shape);
+}
@@ -1,13 +1,20 @@
 use crate::Application;
-use mint::Point2;
-use winit::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+use mint::{Point2, Vector2};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+/// Pixels scrolled by a touchpad that count as one line of a mouse wheel
+const PIXELS_PER_LINE: f32 = 20.0;
 
 pub enum Event {
     KeyPressed(VirtualKeyCode),
     KeyReleased(VirtualKeyCode),
     MouseButtonPressed(MouseButton, Point2<f64>),
     MouseButtonReleased(MouseButton, Point2<f64>),
-    WindowResize,
+    /// In lines, a positive y is scrolling up
+    MouseScrolled(Vector2<f32>),
+    /// New physical size of the window
+    WindowResize(u32, u32),
+    /// Comes after a `WindowResize` to the new physical size
     ScaleFactorChanged,
 }
 
@@ -18,7 +25,10 @@ pub fn process_event(app: &mut Application, event: &winit::event::Event<()>) ->
         match event {
             WindowEvent::Resized(physical_size) => {
                 app.renderer.api.resize(*physical_size, None);
-                return Some(Event::WindowResize);
+                return Some(Event::WindowResize(
+                    physical_size.width,
+                    physical_size.height,
+                ));
             }
             WindowEvent::ScaleFactorChanged {
                 new_inner_size,
@@ -50,6 +60,16 @@ pub fn process_event(app: &mut Application, event: &winit::event::Event<()>) ->
                     )),
                 };
             }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => (
+                        position.x as f32 / PIXELS_PER_LINE,
+                        position.y as f32 / PIXELS_PER_LINE,
+                    ),
+                };
+                return Some(Event::MouseScrolled(Vector2::from([x, y])));
+            }
             _ => {}
         }
     }
@@ -53,6 +53,10 @@ impl InputContext {
         self.keys_pressed.contains(&key)
     }
 
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_pressed.contains(&button)
+    }
+
     pub fn is_key_released(&self, key: VirtualKeyCode) -> bool {
         if let Some(key_released) = self.key_released {
             if key_released == key {
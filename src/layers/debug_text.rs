@@ -1,15 +1,192 @@
 use super::Layer;
-use crate::Application;
-use wgpu_glyph::{ab_glyph, GlyphBrush, GlyphBrushBuilder, Section, Text};
+use crate::{event::Event, input::VirtualKeyCode, renderer::camera::Camera, Application};
+use anyhow::{anyhow, Context, Result};
+use glam::{Vec2, Vec3, Vec4};
+use std::path::Path;
+use wgpu_glyph::{
+    ab_glyph::FontArc, FontId, GlyphBrush, GlyphBrushBuilder, HorizontalAlign, Layout, Section,
+    Text,
+};
 
+/// Where queued text goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextPosition {
+    /// In logical pixels from the top left of the window
+    Screen(Vec2),
+    /// Projected with the camera of the debug drawing, see `DebugDraw::set_camera`
+    World(Vec3),
+}
+
+/// Horizontal alignment relative to the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Vec4,
+    /// In logical pixels
+    pub size: f32,
+    pub align: TextAlign,
+    /// `FontId(0)` is the embedded Inconsolata, see `DebugText::add_font`
+    pub font: FontId,
+    /// Lines longer than this are wrapped, in logical pixels
+    pub wrap_width: Option<f32>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            color: Vec4::one(),
+            size: 16.0,
+            align: TextAlign::Left,
+            font: FontId(0),
+            wrap_width: None,
+        }
+    }
+}
+
+struct QueuedText {
+    position: TextPosition,
+    text: String,
+    style: TextStyle,
+}
+
+/// Text drawn for a single frame by the `DebugTextLayer`
+///
+/// Text can be queued from any layer, from `on_update` as well as `on_render`, and may span
+/// multiple lines.
+/// ```rust,ignore
+///     app.debug_text.screen(Vec2::new(10.0, 30.0), "Paused");
+///     app.debug_text.queue(
+///         TextPosition::World(enemy_position),
+///         &format!("hp: {}\nstate: {:?}", enemy.hp, enemy.state),
+///         TextStyle { align: TextAlign::Center, ..TextStyle::default() },
+///     );
+/// ```
+pub struct DebugText {
+    /// FPS, frame time and draw calls in the top left corner
+    pub show_stats: bool,
+    pub stats_key: VirtualKeyCode,
+    queue: Vec<QueuedText>,
+    /// Text at the start of the queue that was already there when the last frame started
+    stale: usize,
+    /// Added to the brush of the layer in order, after the embedded font
+    fonts: Vec<FontArc>,
+}
+
+impl Default for DebugText {
+    fn default() -> Self {
+        Self {
+            show_stats: false,
+            stats_key: VirtualKeyCode::F2,
+            queue: vec![],
+            stale: 0,
+            fonts: vec![],
+        }
+    }
+}
+
+impl DebugText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn queue(&mut self, position: TextPosition, text: &str, style: TextStyle) {
+        self.queue.push(QueuedText {
+            position,
+            text: String::from(text),
+            style,
+        });
+    }
+
+    /// Drops the text queued before the last frame that wasn't drawn, called at the start of every
+    /// frame. Text queued after the `DebugTextLayer` rendered is drawn the next frame
+    pub fn update(&mut self) {
+        let stale = self.stale.min(self.queue.len());
+        self.queue.drain(..stale);
+        self.stale = self.queue.len();
+    }
+
+    fn take_queue(&mut self) -> Vec<QueuedText> {
+        self.stale = 0;
+        std::mem::take(&mut self.queue)
+    }
+
+    /// White text in logical pixels from the top left of the window
+    pub fn screen(&mut self, position: Vec2, text: &str) {
+        self.queue(TextPosition::Screen(position), text, TextStyle::default());
+    }
+
+    /// White text at a world position
+    pub fn world(&mut self, position: Vec3, text: &str) {
+        self.queue(TextPosition::World(position), text, TextStyle::default());
+    }
+
+    /// TTF or OTF font, returns the id to use in `TextStyle::font`
+    pub fn add_font(&mut self, bytes: Vec<u8>) -> Result<FontId> {
+        let font = FontArc::try_from_vec(bytes).map_err(|_| anyhow!("Invalid font"))?;
+        self.fonts.push(font);
+        Ok(FontId(self.fonts.len()))
+    }
+
+    pub fn load_font<P: AsRef<Path>>(&mut self, path: P) -> Result<FontId> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read font {:?}", path))?;
+        self.add_font(bytes)
+            .with_context(|| format!("Failed to load font {:?}", path))
+    }
+}
+
+/// Draws the text queued in `app.debug_text`, the stats and the debug drawing of
+/// `app.debug_draw`
+///
+/// Push it as an overlay so it's drawn on top of everything and isn't post processed.
 #[derive(Default)]
 pub struct DebugTextLayer {
     glyph_brush: Option<GlyphBrush<()>>,
+    /// Fonts of `app.debug_text` already in the brush
+    font_count: usize,
+    /// Smoothed so the stats can be read
+    frame_time: f32,
 }
 
 impl DebugTextLayer {
     pub fn new() -> Self {
-        Self { glyph_brush: None }
+        Self::default()
+    }
+
+    fn queue_stats(&mut self, app: &mut Application) {
+        let delta_t = app.delta_t.as_secs_f32();
+        self.frame_time = if self.frame_time == 0.0 {
+            delta_t
+        } else {
+            self.frame_time * 0.95 + delta_t * 0.05
+        };
+        if !app.debug_text.show_stats {
+            return;
+        }
+        let stats = app.renderer.stats();
+        let stats_2d = app.renderer_2d.stats();
+        app.debug_text.queue(
+            TextPosition::Screen(Vec2::new(8.0, 8.0)),
+            &format!(
+                "{}\nfps: {:.0}\nframe time: {:.2}ms\ndraw calls: {}\n2D quads: {}",
+                app.name,
+                1.0 / self.frame_time.max(std::f32::EPSILON),
+                self.frame_time * 1000.0,
+                stats.draw_calls,
+                stats_2d.quads,
+            ),
+            TextStyle {
+                color: Vec4::new(1.0, 1.0, 0.0, 1.0),
+                ..TextStyle::default()
+            },
+        );
     }
 }
 
@@ -19,15 +196,14 @@ impl Layer for DebugTextLayer {
     }
 
     fn on_attach(&mut self, app: &mut Application) {
-        let font = match ab_glyph::FontArc::try_from_slice(include_bytes!(
-            "../assets/Inconsolata-Regular.ttf"
-        )) {
-            Ok(font) => font,
-            Err(_) => {
-                log::error!("Font failed to load");
-                return;
-            }
-        };
+        let font =
+            match FontArc::try_from_slice(include_bytes!("../assets/Inconsolata-Regular.ttf")) {
+                Ok(font) => font,
+                Err(_) => {
+                    log::error!("Font failed to load, debug text is disabled");
+                    return;
+                }
+            };
 
         self.glyph_brush = Some(
             GlyphBrushBuilder::using_font(font)
@@ -35,12 +211,93 @@ impl Layer for DebugTextLayer {
         );
     }
 
+    fn on_event(&mut self, app: &mut Application, event: &Event) {
+        if let Event::KeyPressed(key) = event {
+            if *key == app.debug_draw.toggle_key {
+                app.debug_draw.enabled = !app.debug_draw.enabled;
+            }
+            if *key == app.debug_text.stats_key {
+                app.debug_text.show_stats = !app.debug_text.show_stats;
+            }
+        }
+    }
+
     fn on_render(&mut self, app: &mut Application, frame: &wgpu::SwapChainOutput) {
-        let glyph_brush = self.glyph_brush.as_mut().unwrap();
-        glyph_brush.queue(Section {
-            text: vec![Text::new(&format!("Hello world from: {:?}", app.name))],
-            ..Section::default()
-        });
+        app.debug_draw.render(
+            &mut app.renderer,
+            &app.camera_controller.camera,
+            frame.into(),
+        );
+
+        self.queue_stats(app);
+        if app.debug_draw.enabled {
+            for label in app.debug_draw.labels() {
+                let style = TextStyle {
+                    color: label.color,
+                    ..TextStyle::default()
+                };
+                app.debug_text
+                    .queue(TextPosition::World(label.position), &label.text, style);
+            }
+        }
+        let queue = app.debug_text.take_queue();
+
+        let glyph_brush = match self.glyph_brush.as_mut() {
+            Some(glyph_brush) => glyph_brush,
+            None => return,
+        };
+        for font in app.debug_text.fonts.iter().skip(self.font_count) {
+            glyph_brush.add_font(font.clone());
+        }
+        self.font_count = app.debug_text.fonts.len();
+
+        let api = &app.renderer.api;
+        let scale_factor = api.scale_factor() as f32;
+        let view_projection = app
+            .debug_draw
+            .camera(&app.camera_controller.camera)
+            .view_projection_matrix();
+        for text in queue.iter() {
+            let position = match text.position {
+                TextPosition::Screen(position) => position * scale_factor,
+                TextPosition::World(position) => {
+                    let clip = view_projection * position.extend(1.0);
+                    if clip.w() <= 0.0 {
+                        continue;
+                    }
+                    let ndc = clip.truncate() / clip.w();
+                    let screen = api.ndc_to_screen(Vec2::new(ndc.x(), ndc.y()));
+                    Vec2::new(screen.x as f32, screen.y as f32)
+                }
+            };
+            let mut style = text.style;
+            if style.font.0 > self.font_count {
+                log::warn!("Unknown font {:?}, the default font is used", style.font);
+                style.font = FontId(0);
+            }
+            let align = match style.align {
+                TextAlign::Left => HorizontalAlign::Left,
+                TextAlign::Center => HorizontalAlign::Center,
+                TextAlign::Right => HorizontalAlign::Right,
+            };
+            let wrap_width = style
+                .wrap_width
+                .map_or(std::f32::INFINITY, |width| width * scale_factor);
+            glyph_brush.queue(Section {
+                screen_position: (position.x(), position.y()),
+                bounds: (wrap_width, std::f32::INFINITY),
+                layout: Layout::default_wrap().h_align(align),
+                text: vec![Text::new(&text.text)
+                    .with_color([
+                        style.color.x(),
+                        style.color.y(),
+                        style.color.z(),
+                        style.color.w(),
+                    ])
+                    .with_scale(style.size * scale_factor)
+                    .with_font_id(style.font)],
+            });
+        }
 
         if glyph_brush
             .draw_queued(
@@ -1,7 +1,15 @@
 use super::Layer;
-use crate::Application;
+use crate::{
+    renderer::{
+        particles::{Burst, Curve, Lerp, ParticleEmitter, SimulationSpace},
+        post_process::{Effect, PostProcessStack},
+        render_graph::RenderGraphInfo,
+        renderer_api::{RendererApi, SAMPLE_COUNTS},
+    },
+    Application,
+};
 use derive_new::new;
-use imgui::{im_str, Condition, FontSource};
+use imgui::{im_str, ColorEdit, Condition, FontSource, Slider};
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 use std::path::PathBuf;
 
@@ -20,6 +28,8 @@ pub struct ImguiLayer {
     show_demo_window: bool,
     #[new(value = "true")]
     v_sync_checked: bool,
+    #[new(value = "1")]
+    sample_count: u32,
 }
 
 impl Layer for ImguiLayer {
@@ -29,6 +39,7 @@ impl Layer for ImguiLayer {
 
     fn on_attach(&mut self, app: &mut Application) {
         self.v_sync_checked = app.v_sync;
+        self.sample_count = app.renderer.api.sample_count();
         self.state = Some(ImguiState::new(app, app.imgui_ini_path.clone()));
     }
 
@@ -51,6 +62,13 @@ impl Layer for ImguiLayer {
                 app.renderer.api.set_v_sync(app.v_sync);
             }
 
+            if self.sample_count != app.renderer.api.sample_count() {
+                if let Err(e) = app.renderer.set_sample_count(self.sample_count) {
+                    log::error!("{:?}", e);
+                    self.sample_count = app.renderer.api.sample_count();
+                }
+            }
+
             platform
                 .prepare_frame(context.io_mut(), &app.window)
                 .expect("Failed to prepare frame");
@@ -72,6 +90,19 @@ impl Layer for ImguiLayer {
             .build(&ui, || {
                 ui.text(im_str!("Frametime: {:.2?}", app.delta_t));
                 ui.text(im_str!("FPS: {:.0?}", fps));
+                let stats = app.renderer.stats();
+                ui.text(im_str!(
+                    "Passes: {} Draw calls: {} Pipeline switches: {}",
+                    stats.passes,
+                    stats.draw_calls,
+                    stats.pipeline_switches
+                ));
+                let stats_2d = app.renderer_2d.stats();
+                ui.text(im_str!(
+                    "2D quads: {} batches: {}",
+                    stats_2d.quads,
+                    stats_2d.batches
+                ));
                 ui.separator();
                 let mouse_pos = ui.io().mouse_pos;
                 ui.text(im_str!(
@@ -79,7 +110,25 @@ impl Layer for ImguiLayer {
                     mouse_pos[0],
                     mouse_pos[1]
                 ));
+                ui.text(im_str!(
+                    "Mouse World Position: ({:.2},{:.2})",
+                    app.mouse_world_position.x(),
+                    app.mouse_world_position.y()
+                ));
                 ui.checkbox(im_str!("v-sync"), &mut self.v_sync_checked);
+                render_graph_info(ui, app.renderer.graph_info());
+                if let Some(stack) = app.post_process.as_mut() {
+                    post_process(ui, stack);
+                }
+                ui.text(im_str!("MSAA"));
+                for &sample_count in SAMPLE_COUNTS.iter() {
+                    ui.same_line(0.0);
+                    ui.radio_button(
+                        &im_str!("{}x", sample_count),
+                        &mut self.sample_count,
+                        sample_count,
+                    );
+                }
             });
 
         ui.show_demo_window(&mut self.show_demo_window);
@@ -107,6 +156,249 @@ impl Layer for ImguiLayer {
     }
 }
 
+fn render_graph_info(ui: &imgui::Ui, info: &RenderGraphInfo) {
+    if info.passes.is_empty() || !ui.collapsing_header(im_str!("Render graph")).build() {
+        return;
+    }
+
+    ui.text(im_str!("Passes"));
+    for (i, pass) in info.passes.iter().enumerate() {
+        let label = if pass.culled {
+            im_str!("  culled {}", pass.name)
+        } else {
+            im_str!("  {} {}", i, pass.name)
+        };
+        ui.text(&label);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(format!(
+                "reads: {}\nwrites: {}",
+                pass.reads.join(", "),
+                pass.writes.join(", ")
+            ));
+        }
+    }
+
+    ui.text(im_str!("Resources"));
+    for resource in info.resources.iter() {
+        match resource.lifetime {
+            Some((first, last)) => ui.text(im_str!(
+                "  {} {}x{} passes {} to {}",
+                resource.name,
+                resource.width,
+                resource.height,
+                first,
+                last
+            )),
+            None => ui.text(im_str!("  {} unused", resource.name)),
+        }
+    }
+    ui.text(im_str!("Pooled targets: {}", info.pooled_targets));
+}
+
+fn post_process(ui: &imgui::Ui, stack: &mut PostProcessStack) {
+    if !ui.collapsing_header(im_str!("Post processing")).build() {
+        return;
+    }
+
+    ui.checkbox(im_str!("Enabled"), &mut stack.enabled);
+    for (i, post_effect) in stack.effects.iter_mut().enumerate() {
+        ui.checkbox(
+            &im_str!("{}##{}", post_effect.effect.name(), i),
+            &mut post_effect.enabled,
+        );
+        if !post_effect.enabled {
+            continue;
+        }
+
+        // the ids need to be unique when an effect is in the stack more than once
+        let slider = |name: &str, range: std::ops::RangeInclusive<f32>, value: &mut f32| {
+            Slider::new(&im_str!("  {}##{}", name, i), range).build(ui, value);
+        };
+        match &mut post_effect.effect {
+            Effect::ToneMapping { exposure } => slider("exposure", 0.0..=5.0, exposure),
+            Effect::Bloom {
+                threshold,
+                intensity,
+                radius,
+            } => {
+                slider("threshold", 0.0..=5.0, threshold);
+                slider("intensity", 0.0..=3.0, intensity);
+                slider("radius", 0.0..=4.0, radius);
+            }
+            Effect::Vignette {
+                intensity,
+                smoothness,
+            } => {
+                slider("intensity", 0.0..=1.0, intensity);
+                slider("smoothness", 0.0..=1.0, smoothness);
+            }
+            Effect::ColorGrading { strength } => slider("strength", 0.0..=1.0, strength),
+            Effect::Fxaa => {}
+            Effect::Crt {
+                curvature,
+                scanline_intensity,
+                scanline_count,
+            } => {
+                slider("curvature", 0.0..=0.5, curvature);
+                slider("scanline intensity", 0.0..=1.0, scanline_intensity);
+                slider("scanline count", 50.0..=1000.0, scanline_count);
+            }
+            Effect::Pixelate { pixel_size } => slider("pixel size", 1.0..=32.0, pixel_size),
+        }
+    }
+}
+
+/// Window editing the definition of an emitter while it plays, `name` must be unique
+///
+/// The definition can be saved to and reloaded from the file the emitter was loaded from.
+pub fn particle_emitter_editor(
+    ui: &imgui::Ui,
+    api: &mut RendererApi,
+    name: &str,
+    emitter: &mut ParticleEmitter,
+) {
+    imgui::Window::new(&im_str!("Particles {}", name))
+        .size([320.0, 480.0], Condition::FirstUseEver)
+        .build(ui, || {
+            ui.text(im_str!("Particles: {}", emitter.particle_count()));
+            if let Some(path) = emitter.path().map(|path| path.display().to_string()) {
+                ui.text(im_str!("{}", path));
+                if ui.button(im_str!("Reload"), [0.0, 0.0]) {
+                    if let Err(e) = emitter.reload(api) {
+                        log::error!("{:?}", e);
+                    }
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Save"), [0.0, 0.0]) {
+                    if let Err(e) = emitter.save() {
+                        log::error!("{:?}", e);
+                    }
+                }
+                ui.same_line(0.0);
+            }
+            if ui.button(im_str!("Restart"), [0.0, 0.0]) {
+                emitter.restart();
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Clear"), [0.0, 0.0]) {
+                emitter.clear();
+            }
+            ui.checkbox(im_str!("Playing"), &mut emitter.playing);
+            ui.separator();
+
+            let definition = &mut emitter.definition;
+            ui.radio_button(
+                im_str!("World"),
+                &mut definition.space,
+                SimulationSpace::World,
+            );
+            ui.same_line(0.0);
+            ui.radio_button(
+                im_str!("Local"),
+                &mut definition.space,
+                SimulationSpace::Local,
+            );
+            Slider::new(im_str!("spawn rate"), 0.0..=500.0).build(ui, &mut definition.spawn_rate);
+            Slider::new(im_str!("max particles"), 0..=10000)
+                .build(ui, &mut definition.max_particles);
+            Slider::new(im_str!("duration"), 0.0..=10.0).build(ui, &mut definition.duration);
+            ui.checkbox(im_str!("looping"), &mut definition.looping);
+            ui.input_float2(im_str!("lifetime"), &mut definition.lifetime)
+                .build();
+            Slider::new(im_str!("spawn radius"), 0.0..=2.0).build(ui, &mut definition.spawn_radius);
+            Slider::new(im_str!("direction"), -180.0..=180.0).build(ui, &mut definition.direction);
+            Slider::new(im_str!("spread"), 0.0..=360.0).build(ui, &mut definition.spread);
+            ui.input_float2(im_str!("speed"), &mut definition.speed)
+                .build();
+            ui.input_float2(im_str!("acceleration"), &mut definition.acceleration)
+                .build();
+            ui.input_float2(im_str!("size"), &mut definition.size)
+                .build();
+            ui.input_float2(im_str!("rotation"), &mut definition.rotation)
+                .build();
+            ui.input_float2(
+                im_str!("angular velocity"),
+                &mut definition.angular_velocity,
+            )
+            .build();
+
+            if ui.collapsing_header(im_str!("Bursts")).build() {
+                let mut removed = None;
+                for (i, burst) in definition.bursts.iter_mut().enumerate() {
+                    Slider::new(
+                        &im_str!("time##burst{}", i),
+                        0.0..=definition.duration.max(0.0),
+                    )
+                    .build(ui, &mut burst.time);
+                    Slider::new(&im_str!("count##burst{}", i), 0..=500).build(ui, &mut burst.count);
+                    if ui.button(&im_str!("Remove##burst{}", i), [0.0, 0.0]) {
+                        removed = Some(i);
+                    }
+                }
+                if let Some(i) = removed {
+                    definition.bursts.remove(i);
+                }
+                if ui.button(im_str!("Add burst"), [0.0, 0.0]) {
+                    definition.bursts.push(Burst {
+                        time: 0.0,
+                        count: 10,
+                    });
+                }
+            }
+
+            if ui.collapsing_header(im_str!("Size over lifetime")).build() {
+                curve(
+                    ui,
+                    "size",
+                    &mut definition.size_over_lifetime,
+                    1.0,
+                    |label, value| {
+                        Slider::new(label, 0.0..=2.0).build(ui, value);
+                    },
+                );
+            }
+            if ui.collapsing_header(im_str!("Color over lifetime")).build() {
+                curve(
+                    ui,
+                    "color",
+                    &mut definition.color_over_lifetime,
+                    [1.0; 4],
+                    |label, value| {
+                        ColorEdit::new(label, value).build(ui);
+                    },
+                );
+            }
+        });
+}
+
+/// Time and value of every key, with buttons to add and remove keys
+fn curve<T: Lerp>(
+    ui: &imgui::Ui,
+    id: &str,
+    curve: &mut Curve<T>,
+    new_value: T,
+    mut value: impl FnMut(&imgui::ImStr, &mut T),
+) {
+    let mut removed = None;
+    for (i, (time, key_value)) in curve.keys.iter_mut().enumerate() {
+        Slider::new(&im_str!("time##{}{}", id, i), 0.0..=1.0).build(ui, time);
+        value(&im_str!("value##{}{}", id, i), key_value);
+        if ui.button(&im_str!("Remove##{}{}", id, i), [0.0, 0.0]) {
+            removed = Some(i);
+        }
+    }
+    if let Some(i) = removed {
+        curve.keys.remove(i);
+    }
+    if ui.button(&im_str!("Add key##{}", id), [0.0, 0.0]) {
+        curve.keys.push((1.0, new_value));
+    }
+    // the keys would swap under the mouse while dragging
+    if !ui.is_any_item_active() {
+        curve.sort();
+    }
+}
+
 struct ImguiState {
     context: Box<imgui::Context>,
     platform: Box<WinitPlatform>,
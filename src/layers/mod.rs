@@ -99,8 +99,20 @@ impl LayerStack {
         }
     }
 
+    /// The post processing stack is applied between the layers and the overlays
     pub fn on_render(&mut self, app: &mut Application, frame: &wgpu::SwapChainOutput) {
-        for layer in self.layers.iter_mut() {
+        // the scene target is resized before the layers draw to it
+        if let Some(stack) = app.post_process.as_mut() {
+            stack.update_targets(&app.renderer.api);
+        }
+        let (layers, overlays) = self.layers.split_at_mut(self.layer_insert);
+        for layer in layers.iter_mut() {
+            layer.on_render(app, frame);
+        }
+        if let Some(stack) = app.post_process.as_mut() {
+            stack.apply(&mut app.renderer, frame);
+        }
+        for layer in overlays.iter_mut() {
             layer.on_render(app, frame);
         }
     }
@@ -1,17 +1,24 @@
+// lets the derive macros use ::hazel paths inside this crate too
+extern crate self as hazel;
+
 pub mod event;
 pub mod input;
 pub mod layers;
 pub mod renderer;
 
-use event::process_event;
+use event::{process_event, Event};
 use input::InputContext;
-use layers::{imgui::ImguiLayer, LayerStack};
-use renderer::{orthographic_camera::OrthographicCamera, renderer_api::RendererApi, Renderer};
+use layers::{debug_text::DebugText, imgui::ImguiLayer, LayerStack};
+use renderer::{
+    debug_draw::DebugDraw, orthographic_camera_controller::OrthographicCameraController,
+    post_process::PostProcessStack, renderer_2d::Renderer2D, renderer_api::RendererApi, Renderer,
+};
 
 pub use imgui::Ui;
 
 use anyhow::Result;
 use futures::executor::block_on;
+use glam::Vec2;
 use std::{
     path::PathBuf,
     time::{Duration, Instant},
@@ -31,8 +38,19 @@ pub struct Application {
     pub input_context: InputContext,
     pub v_sync: bool,
     pub renderer: Renderer,
+    /// Quad batch renderer for sprites, see `Renderer2D::end_scene`
+    pub renderer_2d: Renderer2D,
+    /// Debug lines and labels, drawn by the `DebugTextLayer`
+    pub debug_draw: DebugDraw,
+    /// Text drawn for a frame by the `DebugTextLayer`
+    pub debug_text: DebugText,
+    /// Applied to what the layers draw before the overlays, see `PostProcessStack::target`
+    pub post_process: Option<PostProcessStack>,
+    /// Updated before the layers, set `enabled` to false to move the camera yourself
+    pub camera_controller: OrthographicCameraController,
+    /// Where the mouse is in the world seen by `camera_controller`, updated before the layers
+    pub mouse_world_position: Vec2,
     close_requested: bool,
-    camera: OrthographicCamera,
     window: Box<Window>,
     imgui_ini_path: Option<PathBuf>,
 }
@@ -62,10 +80,12 @@ impl Application {
             .with_visible(false)
             .build(&event_loop)?;
         let v_sync = true;
+        let sample_count = 4;
 
         log::trace!("Window created");
 
-        let camera = OrthographicCamera::new(-1.0, 1.0, -1.0, 1.0);
+        let size = window.inner_size();
+        let camera_controller = OrthographicCameraController::new(size.width, size.height, false);
 
         let clear_color = wgpu::Color {
             r: 0.1,
@@ -74,10 +94,13 @@ impl Application {
             a: 1.0,
         };
 
-        let renderer = {
-            let renderer_api = block_on(RendererApi::new(&window, clear_color, v_sync))?;
+        let mut renderer = {
+            let renderer_api =
+                block_on(RendererApi::new(&window, clear_color, v_sync, sample_count))?;
             Renderer::new(renderer_api)
         };
+        let renderer_2d = Renderer2D::new(&mut renderer.api, &renderer.shader_cache)?;
+        let debug_draw = DebugDraw::new(&mut renderer.api, &renderer.shader_cache)?;
 
         log::trace!("Renderer created");
 
@@ -91,9 +114,14 @@ impl Application {
                 window: Box::new(window),
                 delta_t: Duration::default(),
                 renderer,
+                renderer_2d,
+                debug_draw,
+                debug_text: DebugText::new(),
+                post_process: None,
                 input_context: InputContext::new(),
                 v_sync,
-                camera,
+                camera_controller,
+                mouse_world_position: Vec2::zero(),
                 imgui_ini_path,
                 close_requested: false,
             },
@@ -132,7 +160,15 @@ pub fn run(app: Application, layer_stack: LayerStack, event_loop: EventLoop<()>)
         winit::event::Event::MainEventsCleared => {
             app.delta_t = app.renderer.api.last_frame.elapsed();
             app.renderer.api.last_frame = Instant::now();
-
+            app.debug_draw.update(app.delta_t);
+            app.debug_text.update();
+
+            app.camera_controller
+                .on_update(&app.input_context, app.delta_t);
+            app.mouse_world_position = app
+                .camera_controller
+                .camera
+                .screen_to_world(&app.renderer.api, app.input_context.mouse_position);
             layer_stack.on_update(&mut app);
 
             layer_stack.on_before_render(&mut app);
@@ -158,6 +194,14 @@ pub fn run(app: Application, layer_stack: LayerStack, event_loop: EventLoop<()>)
             layer_stack.on_winit_event(&mut app, &event);
 
             if let Some(event) = process_event(&mut app, &event) {
+                if let Event::ScaleFactorChanged = event {
+                    // the window was resized to its new physical size too
+                    let size = app.renderer.api.size;
+                    let resize = Event::WindowResize(size.width, size.height);
+                    app.camera_controller.on_event(&resize);
+                    layer_stack.on_event(&mut app, &resize);
+                }
+                app.camera_controller.on_event(&event);
                 layer_stack.on_event(&mut app, &event);
             }
         }
@@ -1,11 +1,184 @@
-use std::marker::PhantomData;
+use super::renderer_api::RendererApi;
+use std::{marker::PhantomData, mem, ops::Range};
+
+pub use hazel_derive::VertexBufferLayout;
+
+/// The type of a vertex attribute as seen by the shader
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderDataType {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    Mat3,
+    Mat4,
+    Int,
+    Int2,
+    Int3,
+    Int4,
+    UInt,
+    UInt2,
+    UInt3,
+    UInt4,
+}
+
+impl ShaderDataType {
+    pub fn size(self) -> usize {
+        match self {
+            ShaderDataType::Float | ShaderDataType::Int | ShaderDataType::UInt => 4,
+            ShaderDataType::Float2 | ShaderDataType::Int2 | ShaderDataType::UInt2 => 4 * 2,
+            ShaderDataType::Float3 | ShaderDataType::Int3 | ShaderDataType::UInt3 => 4 * 3,
+            ShaderDataType::Float4 | ShaderDataType::Int4 | ShaderDataType::UInt4 => 4 * 4,
+            ShaderDataType::Mat3 => 4 * 3 * 3,
+            ShaderDataType::Mat4 => 4 * 4 * 4,
+        }
+    }
+
+    /// Matrices take one location per column
+    pub fn location_count(self) -> u32 {
+        match self {
+            ShaderDataType::Mat3 => 3,
+            ShaderDataType::Mat4 => 4,
+            _ => 1,
+        }
+    }
+
+    /// The format of a single location
+    fn format(self) -> wgpu::VertexFormat {
+        match self {
+            ShaderDataType::Float => wgpu::VertexFormat::Float,
+            ShaderDataType::Float2 => wgpu::VertexFormat::Float2,
+            ShaderDataType::Float3 | ShaderDataType::Mat3 => wgpu::VertexFormat::Float3,
+            ShaderDataType::Float4 | ShaderDataType::Mat4 => wgpu::VertexFormat::Float4,
+            ShaderDataType::Int => wgpu::VertexFormat::Int,
+            ShaderDataType::Int2 => wgpu::VertexFormat::Int2,
+            ShaderDataType::Int3 => wgpu::VertexFormat::Int3,
+            ShaderDataType::Int4 => wgpu::VertexFormat::Int4,
+            ShaderDataType::UInt => wgpu::VertexFormat::Uint,
+            ShaderDataType::UInt2 => wgpu::VertexFormat::Uint2,
+            ShaderDataType::UInt3 => wgpu::VertexFormat::Uint3,
+            ShaderDataType::UInt4 => wgpu::VertexFormat::Uint4,
+        }
+    }
+}
+
+/// Rust types usable as a field of a vertex, used by `#[derive(VertexBufferLayout)]`
+pub trait VertexAttribute {
+    const DATA_TYPE: ShaderDataType;
+}
+
+macro_rules! vertex_attribute {
+    ($($ty:ty => $data_type:ident),* $(,)?) => {
+        $(
+            impl VertexAttribute for $ty {
+                const DATA_TYPE: ShaderDataType = ShaderDataType::$data_type;
+            }
+        )*
+    };
+}
+
+vertex_attribute!(
+    f32 => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    [[f32; 3]; 3] => Mat3,
+    [[f32; 4]; 4] => Mat4,
+    i32 => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+    u32 => UInt,
+    [u32; 2] => UInt2,
+    [u32; 3] => UInt3,
+    [u32; 4] => UInt4,
+);
+
+#[derive(Clone, Debug)]
+pub struct BufferElement {
+    pub name: &'static str,
+    pub data_type: ShaderDataType,
+    pub offset: usize,
+}
+
+impl BufferElement {
+    pub fn new(data_type: ShaderDataType, name: &'static str) -> Self {
+        Self {
+            name,
+            data_type,
+            offset: 0,
+        }
+    }
+}
+
+/// Describes the elements of a vertex, they are expected to be tightly packed in declaration order
+#[derive(Clone, Debug)]
+pub struct BufferLayout {
+    elements: Vec<BufferElement>,
+    stride: usize,
+}
+
+impl BufferLayout {
+    pub fn new(elements: Vec<BufferElement>) -> Self {
+        let mut elements = elements;
+        let mut offset = 0;
+        for element in elements.iter_mut() {
+            element.offset = offset;
+            offset += element.data_type.size();
+        }
+
+        Self {
+            elements,
+            stride: offset,
+        }
+    }
+
+    pub fn elements(&self) -> &[BufferElement] {
+        &self.elements
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn location_count(&self) -> u32 {
+        self.elements
+            .iter()
+            .map(|element| element.data_type.location_count())
+            .sum()
+    }
+
+    /// Locations are assigned in order starting at `base_location`
+    pub fn attributes(&self, base_location: u32) -> Vec<wgpu::VertexAttributeDescriptor> {
+        let mut location = base_location;
+        let mut attributes = Vec::with_capacity(self.location_count() as usize);
+        for element in self.elements.iter() {
+            let format = element.data_type.format();
+            let column_size =
+                element.data_type.size() / element.data_type.location_count() as usize;
+            for column in 0..element.data_type.location_count() as usize {
+                attributes.push(wgpu::VertexAttributeDescriptor {
+                    offset: (element.offset + column * column_size) as wgpu::BufferAddress,
+                    format,
+                    shader_location: location,
+                });
+                location += 1;
+            }
+        }
+        attributes
+    }
+}
 
 pub trait VertexBufferLayout {
-    fn descriptor<'a>() -> wgpu::VertexBufferDescriptor<'a>;
+    fn layout() -> BufferLayout;
 }
 
+/// Vertex buffers can always be written to, `create` sizes them to the initial data
+/// and `with_capacity` reserves space for geometry written later
 pub struct VertexBuffer<T> {
     pub buffer: wgpu::Buffer,
+    len: usize,
+    capacity: usize,
     layout: PhantomData<T>,
 }
 
@@ -13,32 +186,448 @@ impl<T> VertexBuffer<T>
 where
     T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable,
 {
+    fn usage() -> wgpu::BufferUsage {
+        // COPY_SRC is needed to keep the content when growing
+        wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC
+    }
+
     pub fn create(device: &wgpu::Device, vertices: &[T]) -> Self {
         Self {
-            buffer: device
-                .create_buffer_with_data(bytemuck::cast_slice(vertices), wgpu::BufferUsage::VERTEX),
+            buffer: create_buffer_with_data(device, bytemuck::cast_slice(vertices), Self::usage()),
+            len: vertices.len(),
+            capacity: vertices.len(),
+            layout: PhantomData,
+        }
+    }
+
+    pub fn with_capacity(device: &wgpu::Device, capacity: usize) -> Self {
+        Self {
+            buffer: create_buffer(device, capacity * mem::size_of::<T>(), Self::usage()),
+            len: 0,
+            capacity: capacity.max(1),
             layout: PhantomData,
         }
     }
 
-    pub fn descriptor<'a>(&self) -> wgpu::VertexBufferDescriptor<'a> {
-        T::descriptor()
+    /// `offset` is in vertices, the buffer grows if the data doesn't fit.
+    /// Panics when the write isn't aligned, see `check_alignment`
+    pub fn write(&mut self, api: &mut RendererApi, offset: usize, vertices: &[T]) {
+        let end = offset + vertices.len();
+        check_alignment(mem::size_of::<T>(), offset, end, self.len);
+        if end > self.capacity {
+            self.capacity = grow(
+                api,
+                &mut self.buffer,
+                mem::size_of::<T>(),
+                self.len,
+                self.capacity,
+                end,
+                Self::usage(),
+            );
+        }
+        api.write_buffer(
+            &self.buffer,
+            (offset * mem::size_of::<T>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(vertices),
+        );
+        self.len = self.len.max(end);
+    }
+
+    /// Replaces the whole content
+    pub fn set_data(&mut self, api: &mut RendererApi, vertices: &[T]) {
+        self.len = 0;
+        self.write(api, 0, vertices);
+    }
+
+    /// Forgets the content without releasing the memory
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 }
 
+/// Types that can be used as indices, u16 or u32
+pub trait IndexType: bytemuck::Pod + bytemuck::Zeroable {
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl IndexType for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+}
+
+impl IndexType for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+}
+
 pub struct IndexBuffer {
     pub buffer: wgpu::Buffer,
     pub count: u32,
     pub format: wgpu::IndexFormat,
+    capacity: usize,
 }
 
 impl IndexBuffer {
-    pub fn create(device: &wgpu::Device, indices: &[u16]) -> Self {
+    fn usage() -> wgpu::BufferUsage {
+        // COPY_SRC is needed to keep the content when growing
+        wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC
+    }
+
+    pub fn create<I: IndexType>(device: &wgpu::Device, indices: &[I]) -> Self {
         Self {
-            buffer: device
-                .create_buffer_with_data(bytemuck::cast_slice(indices), wgpu::BufferUsage::INDEX),
+            buffer: create_buffer_with_data(device, bytemuck::cast_slice(indices), Self::usage()),
             count: indices.len() as u32,
-            format: wgpu::IndexFormat::Uint16,
+            format: I::FORMAT,
+            capacity: indices.len(),
+        }
+    }
+
+    pub fn with_capacity<I: IndexType>(device: &wgpu::Device, capacity: usize) -> Self {
+        Self {
+            buffer: create_buffer(device, capacity * mem::size_of::<I>(), Self::usage()),
+            count: 0,
+            format: I::FORMAT,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// `offset` is in indices, the buffer grows if the data doesn't fit.
+    /// Panics when the write isn't aligned, see `check_alignment`. With `u16` indices the offset
+    /// has to be even, and so does the count unless the write reaches the end of the indices
+    pub fn write<I: IndexType>(&mut self, api: &mut RendererApi, offset: usize, indices: &[I]) {
+        assert!(
+            I::FORMAT == self.format,
+            "Index type doesn't match the format of the index buffer"
+        );
+
+        let end = offset + indices.len();
+        check_alignment(mem::size_of::<I>(), offset, end, self.count as usize);
+        if end > self.capacity {
+            self.capacity = grow(
+                api,
+                &mut self.buffer,
+                mem::size_of::<I>(),
+                self.count as usize,
+                self.capacity,
+                end,
+                Self::usage(),
+            );
         }
+        api.write_buffer(
+            &self.buffer,
+            (offset * mem::size_of::<I>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(indices),
+        );
+        self.count = self.count.max(end as u32);
+    }
+
+    /// Forgets the content without releasing the memory
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
     }
 }
+
+/// A uniform block bound as its own bind group, with the block at binding 0
+///
+/// `T` needs to follow the std140 layout rules of the matching GLSL block
+pub struct UniformBuffer<T> {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    layout: PhantomData<T>,
+}
+
+impl<T> UniformBuffer<T>
+where
+    T: bytemuck::Pod + bytemuck::Zeroable,
+{
+    pub fn create(device: &wgpu::Device, value: &T, visibility: wgpu::ShaderStage) -> Self {
+        let buffer = create_buffer_with_data(
+            device,
+            bytemuck::bytes_of(value),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+            }],
+            label: None,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            bindings: &[wgpu::Binding {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &buffer,
+                    range: 0..std::mem::size_of::<T>() as wgpu::BufferAddress,
+                },
+            }],
+            label: None,
+        });
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+            layout: PhantomData,
+        }
+    }
+
+    pub fn update(&self, api: &mut RendererApi, value: &T) {
+        api.write_buffer(&self.buffer, 0, bytemuck::bytes_of(value));
+    }
+}
+
+/// Uniform blocks are bound at offsets aligned to this many bytes
+const UNIFORM_OFFSET_ALIGNMENT: usize = 256;
+
+/// An array of uniform blocks bound as its own bind group, a draw picks one with a dynamic offset
+///
+/// ```rust,ignore
+///     let offsets = transforms.write(api, &values);
+///     render_pass.set_bind_group(1, &transforms.bind_group, &[offsets[i]]);
+/// ```
+pub struct DynamicUniformBuffer<T> {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    capacity: usize,
+    layout: PhantomData<T>,
+}
+
+impl<T> DynamicUniformBuffer<T>
+where
+    T: bytemuck::Pod + bytemuck::Zeroable,
+{
+    pub fn new(device: &wgpu::Device, capacity: usize, visibility: wgpu::ShaderStage) -> Self {
+        let capacity = capacity.max(1);
+        let buffer = create_buffer(device, capacity * Self::stride(), Self::usage());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::UniformBuffer { dynamic: true },
+            }],
+            label: None,
+        });
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &buffer);
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+            capacity,
+            layout: PhantomData,
+        }
+    }
+
+    fn usage() -> wgpu::BufferUsage {
+        wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST
+    }
+
+    /// Size of a block rounded up to the offset alignment
+    fn stride() -> usize {
+        (mem::size_of::<T>() + UNIFORM_OFFSET_ALIGNMENT - 1) / UNIFORM_OFFSET_ALIGNMENT
+            * UNIFORM_OFFSET_ALIGNMENT
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            bindings: &[wgpu::Binding {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer,
+                    range: 0..mem::size_of::<T>() as wgpu::BufferAddress,
+                },
+            }],
+            label: None,
+        })
+    }
+
+    /// Replaces the whole content, returns the dynamic offset of every value
+    ///
+    /// The copy is recorded on the frame encoder so passes recorded before still see the previous values.
+    pub fn write(&mut self, api: &mut RendererApi, values: &[T]) -> Vec<u32> {
+        if values.len() > self.capacity {
+            self.capacity = values.len().max(self.capacity * 2);
+            self.buffer = create_buffer(&api.device, self.capacity * Self::stride(), Self::usage());
+            self.bind_group =
+                Self::create_bind_group(&api.device, &self.bind_group_layout, &self.buffer);
+        }
+
+        let stride = Self::stride();
+        let mut data = vec![0; values.len() * stride];
+        for (value, block) in values.iter().zip(data.chunks_mut(stride)) {
+            block[..mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(value));
+        }
+        api.write_buffer(&self.buffer, 0, &data);
+
+        (0..values.len()).map(|i| (i * stride) as u32).collect()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Linear allocator for geometry generated every frame, like immediate mode drawing
+///
+/// It rewinds on the first push of a new frame. The copies are recorded on the frame encoder
+/// so the data pushed last frame stays valid for the draws recorded last frame.
+/// When a frame needs more space the buffer grows and keeps that size for the next frames.
+pub struct StreamBuffer<T> {
+    pub buffer: wgpu::Buffer,
+    usage: wgpu::BufferUsage,
+    capacity: usize,
+    cursor: usize,
+    frame_index: u64,
+    layout: PhantomData<T>,
+}
+
+impl<T> StreamBuffer<T>
+where
+    T: bytemuck::Pod + bytemuck::Zeroable,
+{
+    /// `usage` is usually `VERTEX` or `INDEX`
+    pub fn new(device: &wgpu::Device, capacity: usize, usage: wgpu::BufferUsage) -> Self {
+        let usage = usage | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC;
+        Self {
+            buffer: create_buffer(device, capacity * mem::size_of::<T>(), usage),
+            usage,
+            capacity: capacity.max(1),
+            cursor: 0,
+            frame_index: 0,
+            layout: PhantomData,
+        }
+    }
+
+    /// Returns the range of elements the data was written to
+    pub fn push(&mut self, api: &mut RendererApi, data: &[T]) -> Range<u32> {
+        if self.frame_index != api.frame_index {
+            self.frame_index = api.frame_index;
+            self.cursor = 0;
+        }
+
+        // copies start on 4 bytes, u16 indices skip one after an odd count
+        let mut start = self.cursor;
+        while start * mem::size_of::<T>() % 4 != 0 {
+            start += 1;
+        }
+        let end = start + data.len();
+        if end > self.capacity {
+            self.capacity = grow(
+                api,
+                &mut self.buffer,
+                mem::size_of::<T>(),
+                start,
+                self.capacity,
+                end,
+                self.usage,
+            );
+        }
+        api.write_buffer(
+            &self.buffer,
+            (start * mem::size_of::<T>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(data),
+        );
+        self.cursor = end;
+
+        start as u32..end as u32
+    }
+
+    /// Byte offset of an element, for `set_vertex_buffer` and `set_index_buffer`
+    pub fn offset_of(&self, element: u32) -> wgpu::BufferAddress {
+        element as wgpu::BufferAddress * mem::size_of::<T>() as wgpu::BufferAddress
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Copies start on 4 bytes and the staging belt pads them with zeros to 4 bytes. A write can't
+/// start in the middle of 4 bytes, or end in the middle of 4 bytes that still hold data after it
+fn check_alignment(element_size: usize, offset: usize, end: usize, len: usize) {
+    assert!(
+        offset * element_size % 4 == 0,
+        "Buffer writes need to start on 4 bytes, element {} doesn't",
+        offset
+    );
+    assert!(
+        end * element_size % 4 == 0 || end >= len,
+        "Buffer write ending at element {} would overwrite the element after it",
+        end
+    );
+}
+
+/// The size is rounded up to 4 bytes, the staging belt pads its copies to 4 bytes
+fn create_buffer(device: &wgpu::Device, size: usize, usage: wgpu::BufferUsage) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: ((size + 3) & !3).max(4) as wgpu::BufferAddress,
+        usage,
+    })
+}
+
+/// The data is padded with zeros to 4 bytes, like `create_buffer`
+fn create_buffer_with_data(
+    device: &wgpu::Device,
+    data: &[u8],
+    usage: wgpu::BufferUsage,
+) -> wgpu::Buffer {
+    let mut padded = data.to_vec();
+    padded.resize(((data.len() + 3) & !3).max(4), 0);
+    device.create_buffer_with_data(&padded, usage)
+}
+
+/// Replaces the buffer with a bigger one and copies the used part over, returns the new capacity
+fn grow(
+    api: &mut RendererApi,
+    buffer: &mut wgpu::Buffer,
+    element_size: usize,
+    len: usize,
+    capacity: usize,
+    required: usize,
+    usage: wgpu::BufferUsage,
+) -> usize {
+    let new_capacity = required.max(capacity * 2);
+    let new_buffer = create_buffer(&api.device, new_capacity * element_size, usage);
+
+    if len > 0 {
+        api.encoder.copy_buffer_to_buffer(
+            buffer,
+            0,
+            &new_buffer,
+            0,
+            ((len * element_size + 3) & !3) as wgpu::BufferAddress,
+        );
+    }
+    *buffer = new_buffer;
+
+    log::trace!("Buffer grew from {} to {} elements", capacity, new_capacity);
+    new_capacity
+}
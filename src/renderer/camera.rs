@@ -0,0 +1,27 @@
+use glam::{Mat4, Vec3};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// glam projections map the depth to -1..1 like OpenGL, wgpu expects 0..1
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    pub(crate) static ref OPENGL_TO_WGPU_MATRIX: glam::Mat4 = glam::Mat4::from_cols_array(&[
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.0,
+        0.0, 0.0, 0.5, 1.0
+    ]);
+}
+
+/// What `Renderer::begin_scene` needs from a camera
+///
+/// The matrices are expected to be up to date, both cameras recalculate them when they're moved
+/// with `recalculate_view_matrix`.
+pub trait Camera {
+    fn view_matrix(&self) -> Mat4;
+    /// Maps the depth to 0..1 like wgpu expects
+    fn projection_matrix(&self) -> Mat4;
+    fn view_projection_matrix(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+    fn position(&self) -> Vec3;
+}
@@ -0,0 +1,187 @@
+use super::orthographic_camera::OrthographicCamera;
+use glam::{Vec2, Vec3};
+use std::time::Duration;
+
+/// Keeps the camera on a target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Follow {
+    pub target: Vec2,
+    /// How fast the camera catches up, an exponential decay rate: after one second the distance
+    /// left is multiplied by `e^-damping`, so 5 leaves less than 1%. 0 snaps to the target
+    pub damping: f32,
+    /// Half size of the area around the center of the view where the target can move without moving the camera
+    pub dead_zone: Vec2,
+}
+
+impl Follow {
+    pub fn new(target: Vec2) -> Self {
+        Self {
+            target,
+            damping: 5.0,
+            dead_zone: Vec2::zero(),
+        }
+    }
+}
+
+/// Trauma based screen shake, the shake grows with the square of the trauma
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shake {
+    /// 0 to 1, goes back down to 0 over time
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+    /// Offset in world units at full trauma
+    pub max_offset: Vec2,
+    /// Rotation in radians at full trauma
+    pub max_angle: f32,
+    /// How fast the shake changes direction
+    pub frequency: f32,
+    time: f32,
+}
+
+impl Default for Shake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.0,
+            max_offset: Vec2::new(0.3, 0.3),
+            max_angle: 0.05,
+            frequency: 15.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl Shake {
+    /// Offset and angle to apply to the camera this frame
+    fn sample(&self) -> (Vec2, f32) {
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        (
+            Vec2::new(
+                self.max_offset.x() * shake * noise(t, 0.0),
+                self.max_offset.y() * shake * noise(t, 17.0),
+            ),
+            self.max_angle * shake * noise(t, 41.0),
+        )
+    }
+}
+
+/// Edges of the world the view has to stay in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Camera behaviours for 2D games, applied to an `OrthographicCamera` every update
+///
+/// The rig owns the position and rotation of the camera, the `Application::camera_controller`
+/// needs to be disabled when its camera is the one moved by the rig.
+/// ```rust,ignore
+///     fn on_update(&mut self, app: &mut Application) {
+///         self.rig.follow = Some(Follow::new(self.player_position));
+///         if self.player_hit {
+///             self.rig.add_trauma(0.5);
+///         }
+///         self.rig.update(&mut self.camera, app.delta_t);
+///     }
+/// ```
+pub struct CameraRig {
+    /// Where the camera is before the shake and the snapping
+    pub position: Vec2,
+    pub rotation: f32,
+    pub follow: Option<Follow>,
+    pub shake: Shake,
+    pub bounds: Option<WorldBounds>,
+    /// Snaps the camera to whole pixels of the art, in pixels per world unit
+    pub pixels_per_unit: Option<f32>,
+}
+
+impl CameraRig {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            rotation: 0.0,
+            follow: None,
+            shake: Shake::default(),
+            bounds: None,
+            pixels_per_unit: None,
+        }
+    }
+
+    /// Trauma adds up and is clamped to 1
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.shake.trauma = (self.shake.trauma + trauma).max(0.0).min(1.0);
+    }
+
+    pub fn update(&mut self, camera: &mut OrthographicCamera, delta_t: Duration) {
+        let dt = delta_t.as_secs_f32();
+        let half_view = camera.view_size() * 0.5;
+
+        if let Some(follow) = self.follow {
+            let offset = follow.target - self.position;
+            // only the part outside of the dead zone moves the camera
+            let outside =
+                |offset: f32, dead_zone: f32| offset.signum() * (offset.abs() - dead_zone).max(0.0);
+            let desired = self.position
+                + Vec2::new(
+                    outside(offset.x(), follow.dead_zone.x()),
+                    outside(offset.y(), follow.dead_zone.y()),
+                );
+            self.position = if follow.damping > 0.0 {
+                let t = 1.0 - (-follow.damping * dt).exp();
+                self.position + (desired - self.position) * t
+            } else {
+                desired
+            };
+        }
+
+        if let Some(bounds) = self.bounds {
+            // a world smaller than the view stays centered
+            let clamp = |position: f32, min: f32, max: f32, half_view: f32| {
+                if max - min <= half_view * 2.0 {
+                    (min + max) * 0.5
+                } else {
+                    position.max(min + half_view).min(max - half_view)
+                }
+            };
+            self.position = Vec2::new(
+                clamp(
+                    self.position.x(),
+                    bounds.min.x(),
+                    bounds.max.x(),
+                    half_view.x(),
+                ),
+                clamp(
+                    self.position.y(),
+                    bounds.min.y(),
+                    bounds.max.y(),
+                    half_view.y(),
+                ),
+            );
+        }
+
+        self.shake.time += dt;
+        self.shake.trauma = (self.shake.trauma - self.shake.decay * dt).max(0.0);
+        let (offset, angle) = self.shake.sample();
+
+        let mut position = self.position + offset;
+        if let Some(pixels_per_unit) = self.pixels_per_unit {
+            position = Vec2::new(
+                (position.x() * pixels_per_unit).round() / pixels_per_unit,
+                (position.y() * pixels_per_unit).round() / pixels_per_unit,
+            );
+        }
+
+        camera.position = Vec3::new(position.x(), position.y(), camera.position.z());
+        camera.rotation = self.rotation + angle;
+        camera.recalculate_view_matrix();
+    }
+}
+
+/// Smooth noise between -1 and 1, a sum of sines is enough for a shake
+fn noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + (t * 2.3 + seed * 1.7).sin() * 0.5 + (t * 4.7 + seed * 2.9).sin() * 0.25)
+        / 1.75
+}
@@ -0,0 +1,326 @@
+use super::{
+    buffer::{VertexBuffer, VertexBufferLayout},
+    camera::Camera,
+    framebuffer::RenderTarget,
+    material::Material,
+    pipeline::{DepthState, RenderState},
+    primitives::VertexArray,
+    renderer_api::RendererApi,
+    shader::CompileOptions,
+    shader_cache::ShaderCache,
+    Renderer,
+};
+use crate::input::VirtualKeyCode;
+use anyhow::Result;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::{rc::Rc, time::Duration};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, VertexBufferLayout)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+unsafe impl bytemuck::Pod for LineVertex {}
+unsafe impl bytemuck::Zeroable for LineVertex {}
+
+struct Shape {
+    /// Pairs of vertices, one pair per line
+    vertices: Vec<LineVertex>,
+    remaining: Duration,
+}
+
+pub struct DebugLabel {
+    pub position: Vec3,
+    pub text: String,
+    pub color: Vec4,
+    remaining: Duration,
+}
+
+/// Matrices of the camera given to `DebugDraw::set_camera`
+#[derive(Clone, Copy)]
+struct CameraMatrices {
+    view: Mat4,
+    projection: Mat4,
+    position: Vec3,
+}
+
+impl Camera for CameraMatrices {
+    fn view_matrix(&self) -> Mat4 {
+        self.view
+    }
+
+    fn projection_matrix(&self) -> Mat4 {
+        self.projection
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+}
+
+/// Lines drawn on top of everything, in world space, from anywhere
+///
+/// Shapes last for the frame they are drawn in unless a duration is given with `for_duration`.
+/// They are drawn by the `DebugTextLayer`, which also draws the labels and toggles the debug
+/// drawing with `toggle_key`. The camera of `app.camera_controller` is used unless another one
+/// is given with `set_camera`.
+/// ```rust,ignore
+///     app.debug_draw.arrow(position, position + velocity, Vec4::new(1.0, 0.0, 0.0, 1.0));
+///     app.debug_draw
+///         .for_duration(Duration::from_secs(2))
+///         .circle(hit_position, 0.1, Vec4::new(1.0, 1.0, 0.0, 1.0));
+/// ```
+pub struct DebugDraw {
+    pub enabled: bool,
+    pub toggle_key: VirtualKeyCode,
+    /// Segments of a full circle
+    pub circle_segments: u32,
+    material: Material,
+    vertex_array: VertexArray,
+    shapes: Vec<Shape>,
+    labels: Vec<DebugLabel>,
+    /// Duration of the next shape
+    next_duration: Duration,
+    camera: Option<CameraMatrices>,
+}
+
+impl DebugDraw {
+    pub fn new(api: &mut RendererApi, shader_cache: &ShaderCache) -> Result<Self> {
+        let shader = shader_cache.load(
+            include_str!("../assets/shaders/debug/line.vert.glsl"),
+            include_str!("../assets/shaders/debug/line.frag.glsl"),
+            &CompileOptions::default(),
+        )?;
+        let mut material = Material::new(api, Rc::new(shader), &[], &[]);
+        material.render_state = RenderState {
+            depth: DepthState::disabled(),
+            ..RenderState::lines(false)
+        };
+        let mut vertex_array = VertexArray::new();
+        vertex_array
+            .add_vertex_buffer(VertexBuffer::<LineVertex>::with_capacity(&api.device, 1024));
+        Ok(Self {
+            enabled: true,
+            toggle_key: VirtualKeyCode::F3,
+            circle_segments: 32,
+            material,
+            vertex_array,
+            shapes: vec![],
+            labels: vec![],
+            next_duration: Duration::default(),
+            camera: None,
+        })
+    }
+
+    /// The next shape stays for `duration` instead of a single frame
+    pub fn for_duration(&mut self, duration: Duration) -> &mut Self {
+        self.next_duration = duration;
+        self
+    }
+
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Vec4) {
+        self.push(&[(from, to)], color);
+    }
+
+    /// Connects the points, the last one is connected to the first when `closed`
+    pub fn polyline(&mut self, points: &[Vec3], closed: bool, color: Vec4) {
+        let mut lines = points
+            .windows(2)
+            .map(|points| (points[0], points[1]))
+            .collect::<Vec<_>>();
+        if closed && points.len() > 2 {
+            lines.push((points[points.len() - 1], points[0]));
+        }
+        self.push(&lines, color);
+    }
+
+    /// Outline of a rectangle centered on `position`, in the xy plane
+    pub fn rect(&mut self, position: Vec3, size: Vec2, color: Vec4) {
+        let half = (size / 2.0).extend(0.0);
+        let corners = [
+            position - half,
+            position + Vec3::new(half.x(), -half.y(), 0.0),
+            position + half,
+            position + Vec3::new(-half.x(), half.y(), 0.0),
+        ];
+        self.polyline(&corners, true, color);
+    }
+
+    /// Outline of a circle in the xy plane
+    pub fn circle(&mut self, center: Vec3, radius: f32, color: Vec4) {
+        let segments = self.circle_segments.max(3);
+        let points = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::PI * 2.0;
+                center + Vec3::new(angle.cos(), angle.sin(), 0.0) * radius
+            })
+            .collect::<Vec<_>>();
+        self.polyline(&points, true, color);
+    }
+
+    /// Line with a head at `to`, the head is a quarter of the length up to a fixed size
+    pub fn arrow(&mut self, from: Vec3, to: Vec3, color: Vec4) {
+        let direction = to - from;
+        let length = direction.length();
+        if length <= std::f32::EPSILON {
+            return;
+        }
+        let head_length = (length * 0.25).min(0.25);
+        let back = -direction / length * head_length;
+        // the head is in the xy plane, like the other shapes
+        let side = Vec3::new(-back.y(), back.x(), 0.0) * 0.5;
+        self.push(
+            &[(from, to), (to, to + back + side), (to, to + back - side)],
+            color,
+        );
+    }
+
+    /// `cells` columns and rows of `cell_size`, centered on `center` in the xy plane
+    pub fn grid(&mut self, center: Vec3, cell_size: Vec2, cells: (u32, u32), color: Vec4) {
+        let size = cell_size * Vec2::new(cells.0 as f32, cells.1 as f32);
+        let min = center - (size / 2.0).extend(0.0);
+        let mut lines = vec![];
+        for column in 0..=cells.0 {
+            let x = column as f32 * cell_size.x();
+            lines.push((
+                min + Vec3::new(x, 0.0, 0.0),
+                min + Vec3::new(x, size.y(), 0.0),
+            ));
+        }
+        for row in 0..=cells.1 {
+            let y = row as f32 * cell_size.y();
+            lines.push((
+                min + Vec3::new(0.0, y, 0.0),
+                min + Vec3::new(size.x(), y, 0.0),
+            ));
+        }
+        self.push(&lines, color);
+    }
+
+    /// Marks a point with a small cross of `size`
+    pub fn cross(&mut self, position: Vec3, size: f32, color: Vec4) {
+        let half = size / 2.0;
+        self.push(
+            &[
+                (
+                    position - Vec3::new(half, 0.0, 0.0),
+                    position + Vec3::new(half, 0.0, 0.0),
+                ),
+                (
+                    position - Vec3::new(0.0, half, 0.0),
+                    position + Vec3::new(0.0, half, 0.0),
+                ),
+            ],
+            color,
+        );
+    }
+
+    /// Text at a world position, drawn by the `DebugTextLayer`
+    pub fn label(&mut self, position: Vec3, text: &str, color: Vec4) {
+        self.labels.push(DebugLabel {
+            position,
+            text: String::from(text),
+            color,
+            remaining: self.take_duration(),
+        });
+    }
+
+    /// Queued to `app.debug_text` by the `DebugTextLayer` while the debug drawing is enabled
+    pub fn labels(&self) -> impl Iterator<Item = &DebugLabel> {
+        self.labels.iter()
+    }
+
+    /// Forgets the shapes that have been drawn for long enough, called at the start of every frame
+    pub fn update(&mut self, delta_t: Duration) {
+        self.shapes.retain(|shape| shape.remaining > delta_t);
+        for shape in self.shapes.iter_mut() {
+            shape.remaining -= delta_t;
+        }
+        self.labels.retain(|label| label.remaining > delta_t);
+        for label in self.labels.iter_mut() {
+            label.remaining -= delta_t;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+        self.labels.clear();
+    }
+
+    /// Shapes and labels are seen by `camera`, for scenes that aren't drawn with the camera of
+    /// `app.camera_controller`. The matrices are copied, set it again when the camera moves
+    pub fn set_camera(&mut self, camera: &dyn Camera) {
+        self.camera = Some(CameraMatrices {
+            view: camera.view_matrix(),
+            projection: camera.projection_matrix(),
+            position: camera.position(),
+        });
+    }
+
+    /// Goes back to the default camera
+    pub fn reset_camera(&mut self) {
+        self.camera = None;
+    }
+
+    /// The camera given to `set_camera`, or `default`
+    pub fn camera<'a>(&'a self, default: &'a dyn Camera) -> &'a dyn Camera {
+        match &self.camera {
+            Some(camera) => camera,
+            None => default,
+        }
+    }
+
+    /// Draws the lines on top of the target, seen by the camera given to `set_camera`, or by
+    /// `default_camera`
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        default_camera: &dyn Camera,
+        target: RenderTarget,
+    ) {
+        if !self.enabled || self.shapes.is_empty() {
+            return;
+        }
+        let vertices = self
+            .shapes
+            .iter()
+            .flat_map(|shape| shape.vertices.iter().copied())
+            .collect::<Vec<_>>();
+        if let Some(vertex_buffer) = self.vertex_array.vertex_buffer_mut::<LineVertex>(0) {
+            vertex_buffer.set_data(&mut renderer.api, &vertices);
+        }
+
+        let mut queue = renderer.begin_scene(self.camera(default_camera));
+        queue.submit(&self.material, &self.vertex_array, Mat4::identity(), target);
+        renderer.end_scene(queue);
+    }
+
+    fn push(&mut self, lines: &[(Vec3, Vec3)], color: Vec4) {
+        let color = [color.x(), color.y(), color.z(), color.w()];
+        let vertices = lines
+            .iter()
+            .flat_map(|(from, to)| {
+                vec![
+                    LineVertex {
+                        position: [from.x(), from.y(), from.z()],
+                        color,
+                    },
+                    LineVertex {
+                        position: [to.x(), to.y(), to.z()],
+                        color,
+                    },
+                ]
+            })
+            .collect();
+        let remaining = self.take_duration();
+        self.shapes.push(Shape {
+            vertices,
+            remaining,
+        });
+    }
+
+    fn take_duration(&mut self) -> Duration {
+        std::mem::take(&mut self.next_duration)
+    }
+}
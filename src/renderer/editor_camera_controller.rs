@@ -0,0 +1,198 @@
+use super::perspective_camera::PerspectiveCamera;
+use crate::{
+    event::Event,
+    input::{InputContext, MouseButton, VirtualKeyCode},
+};
+use glam::{Quat, Vec3};
+use mint::Point2;
+use std::time::Duration;
+
+/// Pitch is kept a bit away from straight up and down so the orbit doesn't flip
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorCameraMode {
+    /// Left drag orbits around the focal point, middle drag pans and the wheel zooms
+    Orbit,
+    /// Right drag looks around while WASD moves and QE goes down and up, the wheel changes the speed
+    Fly,
+}
+
+/// Moves a `PerspectiveCamera` like the viewport of an editor
+///
+/// ```rust,ignore
+///     fn on_update(&mut self, app: &mut Application) {
+///         self.controller.on_update(&app.input_context, app.delta_t);
+///     }
+///     fn on_event(&mut self, app: &mut Application, event: &Event) {
+///         self.controller.on_event(event);
+///     }
+///     fn on_render(&mut self, app: &mut Application, frame: &Frame) {
+///         let mut scene = app.renderer.begin_scene(&self.controller.camera);
+///         ...
+///     }
+/// ```
+pub struct EditorCameraController {
+    pub camera: PerspectiveCamera,
+    pub mode: EditorCameraMode,
+    /// Input is ignored while disabled, resizes are still handled
+    pub enabled: bool,
+    /// Radians per pixel dragged
+    pub rotation_speed: f32,
+    /// World units per second in fly mode
+    pub fly_speed: f32,
+    pub min_distance: f32,
+    yaw: f32,
+    pitch: f32,
+    focal_point: Vec3,
+    distance: f32,
+    viewport: (u32, u32),
+    last_mouse_position: Option<Point2<f64>>,
+}
+
+impl EditorCameraController {
+    /// 45 degrees of vertical field of view, orbiting the origin from 10 units away
+    pub fn new(width: u32, height: u32) -> Self {
+        let aspect_ratio = width.max(1) as f32 / height.max(1) as f32;
+        let mut controller = Self {
+            camera: PerspectiveCamera::new(std::f32::consts::FRAC_PI_4, aspect_ratio, 0.1, 1000.0),
+            mode: EditorCameraMode::Orbit,
+            enabled: true,
+            rotation_speed: 0.005,
+            fly_speed: 5.0,
+            min_distance: 0.1,
+            yaw: 0.0,
+            pitch: 0.0,
+            focal_point: Vec3::zero(),
+            distance: 10.0,
+            viewport: (width.max(1), height.max(1)),
+            last_mouse_position: None,
+        };
+        controller.update_camera();
+        controller
+    }
+
+    pub fn on_update(&mut self, input: &InputContext, delta_t: Duration) {
+        if !self.enabled {
+            self.last_mouse_position = None;
+            return;
+        }
+
+        let mouse_position = input.mouse_position;
+        let (dx, dy) = match self.last_mouse_position {
+            Some(last) => (
+                (mouse_position.x - last.x) as f32,
+                (mouse_position.y - last.y) as f32,
+            ),
+            None => (0.0, 0.0),
+        };
+        self.last_mouse_position = Some(mouse_position);
+
+        match self.mode {
+            EditorCameraMode::Orbit => {
+                if input.is_mouse_pressed(MouseButton::Left) {
+                    self.rotate(dx, dy);
+                } else if input.is_mouse_pressed(MouseButton::Middle) {
+                    // the focal point moves as much as the cursor on the focal plane
+                    let height = 2.0 * self.distance * (self.camera.fov_y() * 0.5).tan();
+                    let pixel = height / self.viewport.1 as f32;
+                    self.focal_point += (-self.camera.right() * dx + self.camera.up() * dy) * pixel;
+                }
+            }
+            EditorCameraMode::Fly => {
+                if input.is_mouse_pressed(MouseButton::Right) {
+                    // looking around turns the camera in place instead of around the focal point
+                    let position = self.camera.position;
+                    self.rotate(dx, dy);
+                    self.focal_point = position + self.camera.forward() * self.distance;
+                }
+
+                let speed = self.fly_speed * delta_t.as_secs_f32();
+                let mut movement = Vec3::zero();
+                let directions = [
+                    (VirtualKeyCode::W, self.camera.forward()),
+                    (VirtualKeyCode::S, -self.camera.forward()),
+                    (VirtualKeyCode::D, self.camera.right()),
+                    (VirtualKeyCode::A, -self.camera.right()),
+                    (VirtualKeyCode::E, Vec3::unit_y()),
+                    (VirtualKeyCode::Q, -Vec3::unit_y()),
+                ];
+                for (key, direction) in directions.iter() {
+                    if input.is_key_pressed(*key) {
+                        movement += *direction;
+                    }
+                }
+                // the camera moves, the focal point follows at the same distance
+                self.focal_point += movement * speed;
+            }
+        }
+
+        self.update_camera();
+    }
+
+    /// Handles the mouse wheel and window resizes
+    pub fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::MouseScrolled(delta) if self.enabled => match self.mode {
+                EditorCameraMode::Orbit => {
+                    // zooming slows down close to the focal point
+                    self.distance = (self.distance * (1.0 - delta.y * 0.1)).max(self.min_distance);
+                    self.update_camera();
+                }
+                EditorCameraMode::Fly => {
+                    self.fly_speed = (self.fly_speed * (1.0 + delta.y * 0.1)).max(0.1);
+                }
+            },
+            Event::WindowResize(width, height) => self.on_resize(*width, *height),
+            _ => {}
+        }
+    }
+
+    pub fn on_resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.viewport = (width, height);
+        self.camera.on_resize(width, height);
+    }
+
+    pub fn focal_point(&self) -> Vec3 {
+        self.focal_point
+    }
+
+    pub fn set_focal_point(&mut self, focal_point: Vec3) {
+        self.focal_point = focal_point;
+        self.update_camera();
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.max(self.min_distance);
+        self.update_camera();
+    }
+
+    /// Radians, a positive yaw turns left and a positive pitch looks up
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.max(-MAX_PITCH).min(MAX_PITCH);
+        self.update_camera();
+    }
+
+    fn rotate(&mut self, dx: f32, dy: f32) {
+        self.set_rotation(
+            self.yaw - dx * self.rotation_speed,
+            self.pitch - dy * self.rotation_speed,
+        );
+    }
+
+    /// The camera stays `distance` behind the focal point
+    fn update_camera(&mut self) {
+        self.camera.orientation =
+            Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch);
+        self.camera.position = self.focal_point - self.camera.forward() * self.distance;
+        self.camera.recalculate_view_matrix();
+    }
+}
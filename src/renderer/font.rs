@@ -0,0 +1,313 @@
+use super::{
+    renderer_api::RendererApi, sprite_sheet::SpriteSheet, sub_texture::SubTexture2D,
+    texture_atlas::AtlasPacker,
+};
+use ab_glyph::{Font as _, FontVec, GlyphId, PxScale, ScaleFont as _};
+use anyhow::{anyhow, Context, Result};
+use glam::{Vec2, Vec4};
+use image::RgbaImage;
+use std::{collections::HashMap, path::Path};
+
+/// Height in pixels of the glyphs rasterized to build the distance fields
+const SDF_SIZE: f32 = 48.0;
+/// Pixels around each glyph covered by the distance field, also the largest distance encoded
+const SDF_SPREAD: u32 = 6;
+
+/// A character of a `Font`, sizes are relative to the line height of the font
+#[derive(Clone)]
+pub struct Glyph {
+    /// `None` for glyphs without an outline, like the space
+    pub sprite: Option<SubTexture2D>,
+    pub size: Vec2,
+    /// From the pen position on the baseline to the bottom left of the quad
+    pub offset: Vec2,
+    pub advance: f32,
+    id: GlyphId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextOptions {
+    /// World height of a line
+    pub size: f32,
+    pub color: Vec4,
+    /// Fraction of `size`, 0 for no outline
+    pub outline_width: f32,
+    pub outline_color: Vec4,
+    /// Multiplies the distance between the baselines of two lines
+    pub line_spacing: f32,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            size: 0.1,
+            color: Vec4::one(),
+            outline_width: 0.0,
+            outline_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// A TTF or OTF font drawn from signed distance fields, it stays sharp at any size
+///
+/// The distance field of every glyph is generated when the font is loaded and packed in a
+/// single texture.
+/// ```rust,ignore
+///     let font = Font::from_file(&mut app.renderer.api, "assets/fonts/OpenSans-Regular.ttf")?;
+///     app.renderer_2d.draw_text(
+///         &font,
+///         "Score: 42",
+///         Vec3::new(-1.0, 0.8, 0.9),
+///         &TextOptions { size: 0.2, outline_width: 0.05, ..TextOptions::default() },
+///     );
+/// ```
+pub struct Font {
+    font: FontVec,
+    pub sheet: SpriteSheet,
+    glyphs: HashMap<char, Glyph>,
+    /// Distance between two baselines, relative to the height of a line
+    pub line_height: f32,
+    pub ascent: f32,
+    pub descent: f32,
+}
+
+impl Font {
+    pub fn from_file<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read font {:?}", path))?;
+        Self::from_bytes(api, bytes).with_context(|| format!("Failed to load font {:?}", path))
+    }
+
+    /// Printable ASCII and Latin-1 characters
+    pub fn from_bytes(api: &mut RendererApi, bytes: Vec<u8>) -> Result<Self> {
+        let chars = (32u8..127).chain(160..=255).map(char::from);
+        Self::with_chars(api, bytes, chars)
+    }
+
+    /// Only `chars` can be drawn, the others are replaced by '?'
+    pub fn with_chars(
+        api: &mut RendererApi,
+        bytes: Vec<u8>,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<Self> {
+        let font = FontVec::try_from_vec(bytes).map_err(|_| anyhow!("Invalid font"))?;
+        let scaled = font.as_scaled(PxScale::from(SDF_SIZE));
+
+        let mut packer = AtlasPacker::new();
+        // filtering must not bleed the neighbours in the distance field
+        packer.padding = 2;
+        let mut glyphs = HashMap::new();
+        for c in chars {
+            let id = font.glyph_id(c);
+            if id.0 == 0 {
+                continue;
+            }
+            let mut glyph = Glyph {
+                sprite: None,
+                size: Vec2::zero(),
+                offset: Vec2::zero(),
+                advance: scaled.h_advance(id) / SDF_SIZE,
+                id,
+            };
+            if let Some((image, min)) = distance_field(&font, id) {
+                glyph.size = Vec2::new(image.width() as f32, image.height() as f32) / SDF_SIZE;
+                glyph.offset = min / SDF_SIZE;
+                packer.add_image(&c.to_string(), image);
+            }
+            glyphs.insert(c, glyph);
+        }
+
+        let sheet = packer.build(api)?;
+        for (c, glyph) in glyphs.iter_mut() {
+            glyph.sprite = sheet.get(&c.to_string()).cloned();
+        }
+
+        Ok(Self {
+            line_height: (scaled.height() + scaled.line_gap()) / SDF_SIZE,
+            ascent: scaled.ascent() / SDF_SIZE,
+            descent: scaled.descent() / SDF_SIZE,
+            font,
+            sheet,
+            glyphs,
+        })
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// Adjustment of the advance between two characters, relative to the line height
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        match (self.glyphs.get(&first), self.glyphs.get(&second)) {
+            (Some(first), Some(second)) => {
+                self.font
+                    .as_scaled(PxScale::from(SDF_SIZE))
+                    .kern(first.id, second.id)
+                    / SDF_SIZE
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Pen position of every glyph, relative to the start of the baseline of the first line
+    pub fn layout(&self, text: &str, options: &TextOptions) -> Vec<(Vec2, &Glyph)> {
+        let mut glyphs = vec![];
+        let mut pen = Vec2::zero();
+        let mut previous = None;
+        for c in text.chars() {
+            if c == '\n' {
+                pen = Vec2::new(
+                    0.0,
+                    pen.y() - self.line_height * options.line_spacing * options.size,
+                );
+                previous = None;
+                continue;
+            }
+            let glyph = match self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?')) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if let Some(previous) = previous {
+                pen += Vec2::new(self.kerning(previous, c) * options.size, 0.0);
+            }
+            glyphs.push((pen, glyph));
+            pen += Vec2::new(glyph.advance * options.size, 0.0);
+            previous = Some(c);
+        }
+        glyphs
+    }
+
+    /// World size of the text, the width of the longest line and the height of all the lines
+    pub fn measure(&self, text: &str, options: &TextOptions) -> Vec2 {
+        let lines = text.lines().count().max(1) as f32;
+        let width = self
+            .layout(text, options)
+            .iter()
+            .map(|(pen, glyph)| pen.x() + glyph.advance * options.size)
+            .fold(0.0, f32::max);
+        let height = (self.ascent - self.descent) * options.size
+            + (lines - 1.0) * self.line_height * options.line_spacing * options.size;
+        Vec2::new(width, height)
+    }
+
+    /// Outline width of `TextOptions` in the units of the distance field
+    pub fn outline_width(&self, width: f32) -> f32 {
+        (width * SDF_SIZE / (2.0 * SDF_SPREAD as f32))
+            .max(0.0)
+            .min(0.5)
+    }
+}
+
+/// Distance field of a glyph in the alpha channel, 0.5 on the edge and higher inside, and the
+/// offset in pixels of its bottom left corner from the pen position
+fn distance_field(font: &FontVec, id: GlyphId) -> Option<(RgbaImage, Vec2)> {
+    let outline = font.outline_glyph(id.with_scale(PxScale::from(SDF_SIZE)))?;
+    let bounds = outline.px_bounds();
+    let spread = SDF_SPREAD as usize;
+    let width = bounds.width() as usize + spread * 2;
+    let height = bounds.height() as usize + spread * 2;
+
+    let mut inside = vec![false; width * height];
+    outline.draw(|x, y, coverage| {
+        if coverage >= 0.5 {
+            inside[(y as usize + spread) * width + x as usize + spread] = true;
+        }
+    });
+
+    // squared distances to the closest pixel on the other side of the edge
+    let to_inside = distance_transform(&inside, width, height, true);
+    let to_outside = distance_transform(&inside, width, height, false);
+    let mut image = RgbaImage::new(width as u32, height as u32);
+    for (i, pixel) in image.pixels_mut().enumerate() {
+        let distance = to_inside[i].sqrt() - to_outside[i].sqrt();
+        let value = 0.5 - distance / (2.0 * SDF_SPREAD as f32);
+        pixel.0 = [
+            255,
+            255,
+            255,
+            (value.max(0.0).min(1.0) * 255.0).round() as u8,
+        ];
+    }
+
+    // the bounds are in pixels from the pen position with y going down
+    let min = Vec2::new(
+        bounds.min.x - SDF_SPREAD as f32,
+        -bounds.max.y - SDF_SPREAD as f32,
+    );
+    Some((image, min))
+}
+
+/// Squared distance of every pixel to the closest pixel where `mask` is `target`
+///
+/// Felzenszwalb and Huttenlocher's exact transform, along the columns then along the rows.
+fn distance_transform(mask: &[bool], width: usize, height: usize, target: bool) -> Vec<f32> {
+    let mut grid = mask
+        .iter()
+        .map(|&value| if value == target { 0.0 } else { std::f32::MAX })
+        .collect::<Vec<_>>();
+    let mut line = vec![0.0; width.max(height)];
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        let distances = distance_transform_1d(&line[..height]);
+        for y in 0..height {
+            grid[y * width + x] = distances[y];
+        }
+    }
+    for y in 0..height {
+        let distances = distance_transform_1d(&grid[y * width..(y + 1) * width]);
+        grid[y * width..(y + 1) * width].copy_from_slice(&distances);
+    }
+    grid
+}
+
+fn distance_transform_1d(f: &[f32]) -> Vec<f32> {
+    let n = f.len();
+    let mut distances = vec![0.0; n];
+    if n == 0 {
+        return distances;
+    }
+    // parabolas of the lower envelope and where each one starts
+    let mut v = vec![0usize; n];
+    let mut z = vec![0.0f32; n + 1];
+    let mut k = 0;
+    z[0] = std::f32::NEG_INFINITY;
+    z[1] = std::f32::INFINITY;
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * q as f32 - 2.0 * p as f32)
+    };
+    for q in 1..n {
+        if f[q] == std::f32::MAX {
+            continue;
+        }
+        if f[v[k]] == std::f32::MAX {
+            v[k] = q;
+            continue;
+        }
+        let mut s = intersection(q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersection(q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = std::f32::INFINITY;
+    }
+    k = 0;
+    for (q, distance) in distances.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - v[k] as f32;
+        *distance = if f[v[k]] == std::f32::MAX {
+            std::f32::MAX
+        } else {
+            offset * offset + f[v[k]]
+        };
+    }
+    distances
+}
@@ -0,0 +1,314 @@
+use super::{renderer_api::RendererApi, texture::Texture};
+use crate::Frame;
+use anyhow::{anyhow, Result};
+use futures::executor::block_on;
+use std::rc::Rc;
+
+/// Rows copied from a texture to a buffer need to be aligned to this many bytes
+const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// The formats a pipeline draws to, a pipeline can only be used with a target of the same format
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TargetFormat {
+    pub color_formats: Vec<wgpu::TextureFormat>,
+    pub depth: bool,
+    pub sample_count: u32,
+}
+
+/// Where a render pass draws
+#[derive(Clone, Copy)]
+pub enum RenderTarget<'a> {
+    Frame(&'a Frame),
+    Framebuffer(&'a Framebuffer),
+}
+
+impl<'a> From<&'a Frame> for RenderTarget<'a> {
+    fn from(frame: &'a Frame) -> Self {
+        RenderTarget::Frame(frame)
+    }
+}
+
+impl<'a> From<&'a Framebuffer> for RenderTarget<'a> {
+    fn from(framebuffer: &'a Framebuffer) -> Self {
+        RenderTarget::Framebuffer(framebuffer)
+    }
+}
+
+impl<'a> RenderTarget<'a> {
+    pub fn format(&self, api: &RendererApi) -> TargetFormat {
+        match self {
+            RenderTarget::Frame(_) => api.frame_format(),
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.format(),
+        }
+    }
+
+    pub fn same_target(&self, other: &RenderTarget) -> bool {
+        match (self, other) {
+            (RenderTarget::Frame(a), RenderTarget::Frame(b)) => std::ptr::eq(*a, *b),
+            (RenderTarget::Framebuffer(a), RenderTarget::Framebuffer(b)) => std::ptr::eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramebufferSpec {
+    pub width: u32,
+    pub height: u32,
+    /// One color attachment per format, `layout(location = i) out` writes to the attachment i
+    pub color_formats: Vec<wgpu::TextureFormat>,
+    pub depth: bool,
+    /// Multisampled color attachments are resolved at the end of every pass
+    pub sample_count: u32,
+}
+
+impl Default for FramebufferSpec {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            color_formats: vec![wgpu::TextureFormat::Rgba8UnormSrgb],
+            depth: true,
+            sample_count: 1,
+        }
+    }
+}
+
+/// Offscreen render target, the attachments can be sampled like any other texture
+///
+/// ```rust,ignore
+///     let framebuffer = Framebuffer::new(&api.device, FramebufferSpec::default());
+///     renderer.submit(&material, &vertex_array, transform, &framebuffer);
+///     let minimap = Material::new(api, shader, &[], &[("t_Texture", framebuffer.color_attachment(0))]);
+/// ```
+pub struct Framebuffer {
+    spec: FramebufferSpec,
+    /// Resolved when multisampled, so they can be sampled
+    color_attachments: Vec<Rc<Texture>>,
+    /// What passes draw to when multisampled
+    multisampled_views: Vec<wgpu::TextureView>,
+    depth_attachment: Option<Texture>,
+}
+
+impl Framebuffer {
+    pub fn new(device: &wgpu::Device, spec: FramebufferSpec) -> Self {
+        let mut framebuffer = Self {
+            spec,
+            color_attachments: vec![],
+            multisampled_views: vec![],
+            depth_attachment: None,
+        };
+        framebuffer.invalidate(device);
+        framebuffer
+    }
+
+    pub fn spec(&self) -> &FramebufferSpec {
+        &self.spec
+    }
+
+    pub fn width(&self) -> u32 {
+        self.spec.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.spec.height
+    }
+
+    /// Recreates every attachment, materials using the previous attachments need to be updated
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if width == 0 || height == 0 || (width == self.spec.width && height == self.spec.height) {
+            return;
+        }
+        self.spec.width = width;
+        self.spec.height = height;
+        self.invalidate(device);
+    }
+
+    /// Recreates every attachment, like `resize`
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if sample_count == self.spec.sample_count {
+            return;
+        }
+        self.spec.sample_count = sample_count;
+        self.invalidate(device);
+    }
+
+    fn invalidate(&mut self, device: &wgpu::Device) {
+        let FramebufferSpec {
+            width,
+            height,
+            sample_count,
+            ..
+        } = self.spec;
+        self.color_attachments = self
+            .spec
+            .color_formats
+            .iter()
+            .map(|format| {
+                Rc::new(Texture::create_render_target(
+                    device,
+                    width,
+                    height,
+                    *format,
+                    Some("Framebuffer color attachment"),
+                ))
+            })
+            .collect();
+        self.multisampled_views = if sample_count > 1 {
+            self.spec
+                .color_formats
+                .iter()
+                .map(|format| {
+                    device
+                        .create_texture(&wgpu::TextureDescriptor {
+                            label: Some("Framebuffer multisampled color attachment"),
+                            size: wgpu::Extent3d {
+                                width,
+                                height,
+                                depth: 1,
+                            },
+                            array_layer_count: 1,
+                            mip_level_count: 1,
+                            sample_count,
+                            dimension: wgpu::TextureDimension::D2,
+                            format: *format,
+                            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                        })
+                        .create_default_view()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        self.depth_attachment = if self.spec.depth {
+            Some(Texture::create_depth_texture(
+                device,
+                width,
+                height,
+                sample_count,
+            ))
+        } else {
+            None
+        };
+    }
+
+    pub fn color_attachment(&self, index: usize) -> Rc<Texture> {
+        self.color_attachments[index].clone()
+    }
+
+    pub fn depth_attachment(&self) -> Option<&Texture> {
+        self.depth_attachment.as_ref()
+    }
+
+    pub fn format(&self) -> TargetFormat {
+        TargetFormat {
+            color_formats: self.spec.color_formats.clone(),
+            depth: self.spec.depth,
+            sample_count: self.spec.sample_count,
+        }
+    }
+
+    pub fn color_attachment_descriptors(
+        &self,
+        load_op: wgpu::LoadOp,
+        clear_color: wgpu::Color,
+    ) -> Vec<wgpu::RenderPassColorAttachmentDescriptor> {
+        self.color_attachments
+            .iter()
+            .enumerate()
+            .map(|(i, texture)| match self.multisampled_views.get(i) {
+                Some(multisampled_view) => wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: multisampled_view,
+                    resolve_target: Some(&texture.view),
+                    load_op,
+                    store_op: wgpu::StoreOp::Store,
+                    clear_color,
+                },
+                None => wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &texture.view,
+                    resolve_target: None,
+                    load_op,
+                    store_op: wgpu::StoreOp::Store,
+                    clear_color,
+                },
+            })
+            .collect()
+    }
+
+    /// Copies a color attachment back to the cpu, the rows are tightly packed
+    ///
+    /// This waits for everything recorded so far to be done on the gpu, it's meant for screenshots or picking,
+    /// not for something done every frame.
+    pub fn read_pixels(&self, api: &mut RendererApi, index: usize) -> Result<Vec<u8>> {
+        let format = *self
+            .spec
+            .color_formats
+            .get(index)
+            .ok_or_else(|| anyhow!("Framebuffer has no color attachment {}", index))?;
+        let bytes_per_pixel = bytes_per_pixel(format)?;
+        let FramebufferSpec { width, height, .. } = self.spec;
+
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + COPY_BYTES_PER_ROW_ALIGNMENT - 1)
+            / COPY_BYTES_PER_ROW_ALIGNMENT
+            * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let size = (padded_bytes_per_row * height) as wgpu::BufferAddress;
+
+        let buffer = api.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Framebuffer readback"),
+            size,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        });
+        api.encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &self.color_attachments[index].texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                offset: 0,
+                bytes_per_row: padded_bytes_per_row,
+                rows_per_image: height,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+        api.flush();
+
+        let future = buffer.map_read(0, size);
+        api.device.poll(wgpu::Maintain::Wait);
+        let mapping =
+            block_on(future).map_err(|_| anyhow!("Failed to map framebuffer readback buffer"))?;
+
+        Ok(mapping
+            .as_slice()
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect())
+    }
+}
+
+fn bytes_per_pixel(format: wgpu::TextureFormat) -> Result<u32> {
+    use wgpu::TextureFormat::*;
+    Ok(match format {
+        R8Unorm | R8Snorm | R8Uint | R8Sint => 1,
+        R16Uint | R16Sint | R16Float | Rg8Unorm | Rg8Snorm | Rg8Uint | Rg8Sint => 2,
+        R32Uint | R32Sint | R32Float | Rg16Uint | Rg16Sint | Rg16Float | Rgba8Unorm
+        | Rgba8UnormSrgb | Rgba8Snorm | Rgba8Uint | Rgba8Sint | Bgra8Unorm | Bgra8UnormSrgb
+        | Rgb10a2Unorm | Rg11b10Float => 4,
+        Rg32Uint | Rg32Sint | Rg32Float | Rgba16Uint | Rgba16Sint | Rgba16Float => 8,
+        Rgba32Uint | Rgba32Sint | Rgba32Float => 16,
+        _ => {
+            return Err(anyhow!(
+                "Can't read back a framebuffer of format {:?}",
+                format
+            ))
+        }
+    })
+}
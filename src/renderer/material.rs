@@ -0,0 +1,326 @@
+use super::{pipeline::RenderState, renderer_api::RendererApi, shader::Shader, texture::Texture};
+use anyhow::{anyhow, Result};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_MATERIAL_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+}
+
+impl UniformValue {
+    /// (alignment, size) following the std140 rules
+    fn layout(&self) -> (usize, usize) {
+        match self {
+            UniformValue::Float(_) | UniformValue::Int(_) => (4, 4),
+            UniformValue::Vec2(_) => (8, 8),
+            UniformValue::Vec3(_) => (16, 12),
+            UniformValue::Vec4(_) => (16, 16),
+            UniformValue::Mat4(_) => (16, 64),
+        }
+    }
+
+    fn write(&self, bytes: &mut [u8]) {
+        match self {
+            UniformValue::Float(value) => bytes.copy_from_slice(bytemuck::bytes_of(value)),
+            UniformValue::Int(value) => bytes.copy_from_slice(bytemuck::bytes_of(value)),
+            UniformValue::Vec2(value) => {
+                let value: [f32; 2] = (*value).into();
+                bytes.copy_from_slice(bytemuck::cast_slice(&value))
+            }
+            UniformValue::Vec3(value) => {
+                let value: [f32; 3] = (*value).into();
+                bytes.copy_from_slice(bytemuck::cast_slice(&value))
+            }
+            UniformValue::Vec4(value) => {
+                let value: [f32; 4] = (*value).into();
+                bytes.copy_from_slice(bytemuck::cast_slice(&value))
+            }
+            UniformValue::Mat4(value) => {
+                bytes.copy_from_slice(bytemuck::cast_slice(&value.to_cols_array()))
+            }
+        }
+    }
+
+    fn same_type(&self, other: &UniformValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+struct Uniform {
+    name: String,
+    value: UniformValue,
+    offset: usize,
+}
+
+/// A shader with the values of its uniforms and textures
+///
+/// The material is bound at set 2, set 0 and 1 are used by the renderer for the scene and the transform.
+/// Uniforms are packed in declaration order in a block at binding 0,
+/// each texture then takes two bindings, the texture itself followed by its sampler.
+/// ```glsl
+///     layout(set = 2, binding = 0) uniform Material { vec4 u_Color; };
+///     layout(set = 2, binding = 1) uniform texture2D t_Texture;
+///     layout(set = 2, binding = 2) uniform sampler s_Texture;
+/// ```
+pub struct Material {
+    id: u64,
+    shader: Rc<Shader>,
+    uniforms: Vec<Uniform>,
+    textures: Vec<(String, Rc<Texture>)>,
+    uniform_data: Vec<u8>,
+    uniform_buffer: Option<wgpu::Buffer>,
+    /// Shared by the materials with as many uniform blocks and textures
+    pub bind_group_layout: Rc<wgpu::BindGroupLayout>,
+    pub bind_group: wgpu::BindGroup,
+    /// Part of the pipeline, changing it creates a new pipeline on the next submit
+    pub render_state: RenderState,
+    dirty: Cell<bool>,
+}
+
+impl Material {
+    pub fn new(
+        api: &RendererApi,
+        shader: Rc<Shader>,
+        uniforms: &[(&str, UniformValue)],
+        textures: &[(&str, Rc<Texture>)],
+    ) -> Self {
+        let mut size = 0;
+        let uniforms = uniforms
+            .iter()
+            .map(|(name, value)| {
+                let (align, value_size) = value.layout();
+                let offset = (size + align - 1) / align * align;
+                size = offset + value_size;
+                Uniform {
+                    name: String::from(*name),
+                    value: *value,
+                    offset,
+                }
+            })
+            .collect::<Vec<_>>();
+        // blocks are padded to a multiple of a vec4
+        let size = (size + 15) / 16 * 16;
+
+        let mut uniform_data = vec![0; size];
+        for uniform in uniforms.iter() {
+            let (_, value_size) = uniform.value.layout();
+            uniform
+                .value
+                .write(&mut uniform_data[uniform.offset..uniform.offset + value_size]);
+        }
+
+        let uniform_buffer = if uniforms.is_empty() {
+            None
+        } else {
+            Some(api.device.create_buffer_with_data(
+                &uniform_data,
+                wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            ))
+        };
+
+        let textures = textures
+            .iter()
+            .map(|(name, texture)| (String::from(*name), texture.clone()))
+            .collect::<Vec<_>>();
+
+        let bind_group_layout =
+            api.material_bind_group_layout(!uniforms.is_empty(), textures.len());
+        let bind_group = create_bind_group(
+            &api.device,
+            &bind_group_layout,
+            uniform_buffer.as_ref().map(|buffer| (buffer, size)),
+            &textures,
+        );
+
+        Self {
+            id: NEXT_MATERIAL_ID.fetch_add(1, Ordering::Relaxed),
+            shader,
+            uniforms,
+            textures,
+            uniform_data,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            render_state: RenderState::default(),
+            dirty: Cell::new(false),
+        }
+    }
+
+    /// Unique for the lifetime of the application, used to skip binding the same material twice
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Pipelines are shared by the materials of a shader with the same layout
+    pub(crate) fn shader_rc(&self) -> &Rc<Shader> {
+        &self.shader
+    }
+
+    /// Whether there is a uniform block and the number of textures, what the layout is made of
+    pub(crate) fn layout_key(&self) -> (bool, usize) {
+        (self.uniform_buffer.is_some(), self.textures.len())
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<UniformValue> {
+        self.uniforms
+            .iter()
+            .find(|uniform| uniform.name == name)
+            .map(|uniform| uniform.value)
+    }
+
+    /// The new value is uploaded the next time the material is submitted
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) -> Result<()> {
+        let uniform = self
+            .uniforms
+            .iter_mut()
+            .find(|uniform| uniform.name == name)
+            .ok_or_else(|| anyhow!("Material has no uniform named {}", name))?;
+
+        if !uniform.value.same_type(&value) {
+            return Err(anyhow!(
+                "Uniform {} is a {:?}, it can't be set to {:?}",
+                name,
+                uniform.value,
+                value
+            ));
+        }
+
+        let (_, size) = value.layout();
+        value.write(&mut self.uniform_data[uniform.offset..uniform.offset + size]);
+        uniform.value = value;
+        self.dirty.set(true);
+
+        Ok(())
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Rc<Texture>> {
+        self.textures
+            .iter()
+            .find(|(texture_name, _)| texture_name == name)
+            .map(|(_, texture)| texture)
+    }
+
+    pub fn set_texture(
+        &mut self,
+        device: &wgpu::Device,
+        name: &str,
+        texture: Rc<Texture>,
+    ) -> Result<()> {
+        let slot = self
+            .textures
+            .iter_mut()
+            .find(|(texture_name, _)| texture_name == name)
+            .ok_or_else(|| anyhow!("Material has no texture named {}", name))?;
+        slot.1 = texture;
+
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            self.uniform_buffer
+                .as_ref()
+                .map(|buffer| (buffer, self.uniform_data.len())),
+            &self.textures,
+        );
+
+        Ok(())
+    }
+
+    /// Called by the renderer before drawing with this material
+    pub fn upload(&self, api: &mut RendererApi) {
+        if !self.dirty.get() {
+            return;
+        }
+
+        if let Some(buffer) = self.uniform_buffer.as_ref() {
+            api.write_buffer(buffer, 0, &self.uniform_data);
+        }
+        self.dirty.set(false);
+    }
+}
+
+pub(crate) fn create_bind_group_layout(
+    device: &wgpu::Device,
+    has_uniforms: bool,
+    texture_count: usize,
+) -> wgpu::BindGroupLayout {
+    let mut bindings = Vec::with_capacity(1 + texture_count * 2);
+    if has_uniforms {
+        bindings.push(wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+        });
+    }
+    for i in 0..texture_count as u32 {
+        bindings.push(wgpu::BindGroupLayoutEntry {
+            binding: 1 + i * 2,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                dimension: wgpu::TextureViewDimension::D2,
+                component_type: wgpu::TextureComponentType::Float,
+            },
+        });
+        bindings.push(wgpu::BindGroupLayoutEntry {
+            binding: 2 + i * 2,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Sampler { comparison: false },
+        });
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        bindings: &bindings,
+        label: Some("Material bind group layout"),
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: Option<(&wgpu::Buffer, usize)>,
+    textures: &[(String, Rc<Texture>)],
+) -> wgpu::BindGroup {
+    let mut bindings = Vec::with_capacity(1 + textures.len() * 2);
+    if let Some((buffer, size)) = uniform_buffer {
+        bindings.push(wgpu::Binding {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer {
+                buffer,
+                range: 0..size as wgpu::BufferAddress,
+            },
+        });
+    }
+    for (i, (_, texture)) in textures.iter().enumerate() {
+        let i = i as u32;
+        bindings.push(wgpu::Binding {
+            binding: 1 + i * 2,
+            resource: wgpu::BindingResource::TextureView(&texture.view),
+        });
+        bindings.push(wgpu::Binding {
+            binding: 2 + i * 2,
+            resource: wgpu::BindingResource::Sampler(&texture.sampler),
+        });
+    }
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        bindings: &bindings,
+        label: Some("Material bind group"),
+    })
+}
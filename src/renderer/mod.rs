@@ -1,73 +1,300 @@
-use crate::Frame;
-use buffer::VertexBufferLayout;
-use derive_new::new;
+use buffer::{DynamicUniformBuffer, UniformBuffer};
+use camera::Camera;
+use framebuffer::{RenderTarget, TargetFormat};
+use glam::Mat4;
+use material::Material;
+use pipeline::RenderState;
 use primitives::VertexArray;
+use render_graph::{RenderGraphInfo, TargetPool};
+use render_pass::{PassLoad, RenderPass, RenderQueue, RenderStats};
 use renderer_api::RendererApi;
 use shader::Shader;
+use shader_cache::ShaderCache;
+use std::{
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
 
 pub mod buffer;
+pub mod camera;
+pub mod camera_rig;
+pub mod debug_draw;
+pub mod editor_camera_controller;
+pub mod font;
+pub mod framebuffer;
+pub mod material;
+pub mod nine_slice;
 pub mod orthographic_camera;
+pub mod orthographic_camera_controller;
+pub mod particles;
+pub mod perspective_camera;
 pub mod pipeline;
+pub mod post_process;
 pub mod primitives;
+pub mod render_graph;
+pub mod render_pass;
+pub mod renderer_2d;
 pub mod renderer_api;
 pub mod shader;
+pub mod shader_cache;
+pub mod sprite_animation;
+pub mod sprite_sheet;
+pub mod staging_belt;
+pub mod sub_texture;
+pub mod texture;
+pub mod texture_atlas;
+pub mod tiled;
+pub mod tilemap;
 
 pub enum RenderCommand<'a> {
-    Clear(&'a Frame),
+    Clear(RenderTarget<'a>),
     SetClearColor([f64; 4]),
     // DrawIndexed, // TODO make a pipeline trait?
 }
 
-#[derive(new)]
+/// Bound at set 0, binding 0
+/// ```glsl
+///     layout(set = 0, binding = 0) uniform Scene { mat4 u_ViewProjection; };
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SceneUniforms {
+    pub view_projection: [f32; 16],
+}
+unsafe impl bytemuck::Pod for SceneUniforms {}
+unsafe impl bytemuck::Zeroable for SceneUniforms {}
+
+/// Bound at set 1, binding 0 with a dynamic offset, one per draw
+/// ```glsl
+///     layout(set = 1, binding = 0) uniform Transform { mat4 u_Transform; };
+/// ```
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TransformUniforms {
+    pub transform: [f32; 16],
+}
+unsafe impl bytemuck::Pod for TransformUniforms {}
+unsafe impl bytemuck::Zeroable for TransformUniforms {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    shader: u64,
+    material_layout: (bool, usize),
+    vertex_layout: u64,
+    render_state: RenderState,
+    target: TargetFormat,
+}
+
 pub struct Renderer {
     pub api: RendererApi,
-    #[new(value = "[0.0; 4]")]
+    /// Every shader of the engine is loaded through it, in the `shader_cache` directory next to the
+    /// executable
+    pub shader_cache: ShaderCache,
     clear_color: [f64; 4],
+    scene_uniforms: UniformBuffer<SceneUniforms>,
+    transform_uniforms: DynamicUniformBuffer<TransformUniforms>,
+    /// Dropped with their shader
+    pipelines: HashMap<PipelineKey, (wgpu::RenderPipeline, Weak<Shader>)>,
+    stats: RenderStats,
+    last_stats: RenderStats,
+    stats_frame_index: u64,
+    target_pool: TargetPool,
+    graph_info: RenderGraphInfo,
 }
 
 impl Renderer {
-    pub fn begin_scene(&mut self) {}
+    pub fn new(api: RendererApi) -> Self {
+        let scene_uniforms = UniformBuffer::create(
+            &api.device,
+            &SceneUniforms {
+                view_projection: Mat4::identity().to_cols_array(),
+            },
+            wgpu::ShaderStage::VERTEX,
+        );
+        let transform_uniforms =
+            DynamicUniformBuffer::new(&api.device, 64, wgpu::ShaderStage::VERTEX);
 
-    pub fn end_scene(&mut self) {}
+        Self {
+            api,
+            shader_cache: ShaderCache::new(ShaderCache::default_dir()),
+            clear_color: [0.0; 4],
+            scene_uniforms,
+            transform_uniforms,
+            pipelines: HashMap::new(),
+            stats: RenderStats::default(),
+            last_stats: RenderStats::default(),
+            stats_frame_index: 0,
+            target_pool: TargetPool::default(),
+            graph_info: RenderGraphInfo::default(),
+        }
+    }
+
+    /// Pipelines created with the previous sample count are dropped
+    pub fn set_sample_count(&mut self, sample_count: u32) -> anyhow::Result<()> {
+        self.api.set_sample_count(sample_count)?;
+        self.pipelines.clear();
+        Ok(())
+    }
+
+    /// Draws can be submitted to the returned queue until `end_scene`
+    pub fn begin_scene<'a>(&mut self, camera: &dyn Camera) -> RenderQueue<'a> {
+        self.scene_uniforms.update(
+            &mut self.api,
+            &SceneUniforms {
+                view_projection: camera.view_projection_matrix().to_cols_array(),
+            },
+        );
+        RenderQueue::new()
+    }
+
+    /// Records every pass of the queue
+    pub fn end_scene(&mut self, queue: RenderQueue) {
+        for pass in queue.passes {
+            self.end_pass(pass);
+        }
+    }
 
     pub fn send(&mut self, command: RenderCommand) {
         match command {
-            RenderCommand::Clear(frame) => {
-                self.api.clear(&frame, Some(self.clear_color));
+            RenderCommand::Clear(target) => {
+                self.api.clear(target, Some(self.clear_color));
             }
             RenderCommand::SetClearColor(color) => self.clear_color = color,
         }
     }
 
-    pub fn submit<T>(&mut self, shader: &Shader, vertex_array: &VertexArray<T>, frame: &Frame)
-    where
-        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable,
-    {
-        // FIXME I should probably cache this somehow
-        let pipeline = shader.create_pipeline(&self.api, vertex_array, 1);
+    /// Uses the view projection of the current scene
+    pub fn begin_pass<'a>(
+        &self,
+        target: impl Into<RenderTarget<'a>>,
+        load: PassLoad,
+    ) -> RenderPass<'a> {
+        RenderPass::new(target.into(), load)
+    }
+
+    /// Sorts the draws of the pass and records them in a single wgpu render pass
+    pub fn end_pass(&mut self, mut pass: RenderPass) {
+        self.update_stats_frame();
+        if pass.is_empty() {
+            if let PassLoad::Clear(color) = pass.load {
+                let _ = self
+                    .api
+                    .begin_render_pass(pass.target, wgpu::LoadOp::Clear, color);
+                self.stats.passes += 1;
+            }
+            return;
+        }
+        pass.sort();
 
+        let target_format = pass.target.format(&self.api);
+        let mut keys = Vec::with_capacity(pass.len());
+        let mut transforms = Vec::with_capacity(pass.len());
+        for command in pass.commands.iter() {
+            command.material.upload(&mut self.api);
+            keys.push(self.prepare_pipeline(
+                command.material,
+                command.vertex_array,
+                &target_format,
+            ));
+            transforms.push(TransformUniforms {
+                transform: command.transform.to_cols_array(),
+            });
+        }
+        let offsets = self.transform_uniforms.write(&mut self.api, &transforms);
+
+        let (load_op, clear_color) = match pass.load {
+            PassLoad::Load => (
+                wgpu::LoadOp::Load,
+                wgpu::Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 0.0,
+                },
+            ),
+            PassLoad::Clear(color) => (wgpu::LoadOp::Clear, color),
+        };
         let mut render_pass = self
             .api
-            .encoder
-            .begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    load_op: wgpu::LoadOp::Load,
-                    store_op: wgpu::StoreOp::Store,
-                    clear_color: wgpu::Color {
-                        r: 0.0,
-                        g: 0.0,
-                        b: 0.0,
-                        a: 0.0,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-        render_pass.set_pipeline(&pipeline);
-        // TODO uniforms
-        render_pass.set_vertex_buffer(0, &vertex_array.vertex_buffer.buffer, 0, 0);
-        render_pass.set_index_buffer(&vertex_array.index_buffer.buffer, 0, 0);
-        render_pass.draw_indexed(0..vertex_array.index_buffer.count, 0, 0..1);
+            .begin_render_pass(pass.target, load_op, clear_color);
+        render_pass.set_bind_group(0, &self.scene_uniforms.bind_group, &[]);
+
+        let mut current_pipeline = None;
+        let mut current_material = None;
+        for ((command, key), offset) in pass.commands.iter().zip(keys.iter()).zip(offsets) {
+            if current_pipeline != Some(key) {
+                render_pass.set_pipeline(&self.pipelines[key].0);
+                current_pipeline = Some(key);
+                self.stats.pipeline_switches += 1;
+            }
+            if current_material != Some(command.material.id()) {
+                render_pass.set_bind_group(2, &command.material.bind_group, &[]);
+                current_material = Some(command.material.id());
+            }
+            render_pass.set_bind_group(1, &self.transform_uniforms.bind_group, &[offset]);
+            command.vertex_array.draw(&mut render_pass);
+            self.stats.draw_calls += 1;
+        }
+        self.stats.passes += 1;
+    }
+
+    /// Counters of the last complete frame
+    pub fn stats(&self) -> RenderStats {
+        self.last_stats
+    }
+
+    fn update_stats_frame(&mut self) {
+        if self.stats_frame_index != self.api.frame_index {
+            self.last_stats = if self.stats_frame_index + 1 == self.api.frame_index {
+                self.stats
+            } else {
+                RenderStats::default()
+            };
+            self.stats = RenderStats::default();
+            self.stats_frame_index = self.api.frame_index;
+            self.pipelines
+                .retain(|_, (_, shader)| shader.upgrade().is_some());
+        }
+    }
+
+    /// Creates the pipeline the first time a shader is drawn with a material layout, a vertex layout
+    /// and a render state to a target format
+    fn prepare_pipeline(
+        &mut self,
+        material: &Material,
+        vertex_array: &VertexArray,
+        target_format: &TargetFormat,
+    ) -> PipelineKey {
+        let key = PipelineKey {
+            shader: material.shader().id(),
+            material_layout: material.layout_key(),
+            vertex_layout: vertex_array.layout_id(),
+            render_state: material.render_state,
+            target: target_format.clone(),
+        };
+
+        if !self.pipelines.contains_key(&key) {
+            let layout = self
+                .api
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    bind_group_layouts: &[
+                        &self.scene_uniforms.bind_group_layout,
+                        &self.transform_uniforms.bind_group_layout,
+                        &*material.bind_group_layout,
+                    ],
+                });
+            let pipeline = material.shader().create_pipeline_with_layout(
+                &self.api,
+                &layout,
+                vertex_array,
+                target_format,
+                material.render_state,
+            );
+            self.pipelines
+                .insert(key.clone(), (pipeline, Rc::downgrade(material.shader_rc())));
+        }
+
+        key
     }
 }
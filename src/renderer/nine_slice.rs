@@ -0,0 +1,208 @@
+use super::{renderer_2d::quad_transform, sub_texture::SubTexture2D};
+use glam::{Mat4, Vec2, Vec3};
+
+/// How the edges and the center fill the space between the corners
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceMode {
+    Stretch,
+    /// Repeats the slice at its size, the last one is cut
+    Tile,
+}
+
+/// Size of the borders of the sprite in pixels, they are kept as is when the sprite is scaled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+}
+
+/// A sprite cut in nine by its borders, to draw panels of any size without stretching the corners
+///
+/// ```rust,ignore
+///     let panel = NineSlice::new(sheet.get("panel").unwrap().clone(), Insets::uniform(6.0), 0.01);
+///     app.renderer_2d.draw_nine_slice(Vec3::new(0.0, 0.0, 0.5), Vec2::new(3.0, 1.5), &panel, Vec4::one());
+/// ```
+#[derive(Clone)]
+pub struct NineSlice {
+    pub sprite: SubTexture2D,
+    pub insets: Insets,
+    /// World size of a pixel of the borders
+    pub border_scale: f32,
+    pub edges: SliceMode,
+    pub center: SliceMode,
+}
+
+impl NineSlice {
+    pub fn new(sprite: SubTexture2D, insets: Insets, border_scale: f32) -> Self {
+        Self {
+            sprite,
+            insets,
+            border_scale,
+            edges: SliceMode::Stretch,
+            center: SliceMode::Stretch,
+        }
+    }
+
+    /// Transform and texture coordinates of every quad of a nine slice centered on `position`.
+    /// Borders shrink when `size` is too small to fit them
+    pub fn quads(&self, position: Vec3, size: Vec2) -> Vec<(Mat4, [[f32; 2]; 4])> {
+        let pixels = self.sprite.size();
+        if pixels.x() <= 0.0 || pixels.y() <= 0.0 {
+            return vec![];
+        }
+        let insets = self.insets;
+        let x = axis(
+            size.x(),
+            pixels.x(),
+            insets.left,
+            insets.right,
+            self.border_scale,
+        );
+        let y = axis(
+            size.y(),
+            pixels.y(),
+            insets.bottom,
+            insets.top,
+            self.border_scale,
+        );
+
+        let bottom_left = position.truncate() - size / 2.0;
+        let mut quads = vec![];
+        for (row, y_slice) in y.iter().enumerate() {
+            for (column, x_slice) in x.iter().enumerate() {
+                let mode = match (column, row) {
+                    (1, 1) => self.center,
+                    (1, _) | (_, 1) => self.edges,
+                    _ => SliceMode::Stretch,
+                };
+                // edges only repeat along their length
+                let x_pieces = match mode {
+                    SliceMode::Tile if column == 1 => x_slice.tiles(),
+                    _ => vec![*x_slice],
+                };
+                let y_pieces = match mode {
+                    SliceMode::Tile if row == 1 => y_slice.tiles(),
+                    _ => vec![*y_slice],
+                };
+                for y_piece in &y_pieces {
+                    for x_piece in &x_pieces {
+                        quads.push(self.quad(bottom_left, position.z(), *x_piece, *y_piece));
+                    }
+                }
+            }
+        }
+        quads
+    }
+
+    fn quad(&self, bottom_left: Vec2, z: f32, x: Slice, y: Slice) -> (Mat4, [[f32; 2]; 4]) {
+        let min = bottom_left + Vec2::new(x.start, y.start);
+        let max = bottom_left + Vec2::new(x.end, y.end);
+        let center = (min + max) / 2.0;
+        let transform = quad_transform(center.extend(z), max - min, 0.0);
+
+        // mapping through the corners keeps rotated sprites right
+        let [bl, br, _, tl] = self.sprite.tex_coords();
+        let uv = |u: f32, v: f32| {
+            let tex_coord = bl + (br - bl) * u + (tl - bl) * v;
+            [tex_coord.x(), tex_coord.y()]
+        };
+        (
+            transform,
+            [
+                uv(x.u_start, y.u_start),
+                uv(x.u_end, y.u_start),
+                uv(x.u_end, y.u_end),
+                uv(x.u_start, y.u_end),
+            ],
+        )
+    }
+}
+
+/// Part of an axis, in world units from the start of the nine slice and in texture space
+/// from 0 to 1 along the sprite
+#[derive(Clone, Copy, Debug)]
+struct Slice {
+    start: f32,
+    end: f32,
+    u_start: f32,
+    u_end: f32,
+    /// World length of the whole slice of the texture, for tiling
+    tile_length: f32,
+}
+
+impl Slice {
+    /// Copies of the texture slice at their size, the last one is cut
+    fn tiles(&self) -> Vec<Slice> {
+        if self.tile_length <= std::f32::EPSILON || self.end <= self.start {
+            return vec![*self];
+        }
+        let mut tiles = vec![];
+        let mut start = self.start;
+        while start < self.end - std::f32::EPSILON {
+            let end = (start + self.tile_length).min(self.end);
+            let fraction = (end - start) / self.tile_length;
+            tiles.push(Slice {
+                start,
+                end,
+                u_start: self.u_start,
+                u_end: self.u_start + (self.u_end - self.u_start) * fraction,
+                tile_length: self.tile_length,
+            });
+            start = end;
+        }
+        tiles
+    }
+}
+
+/// Start border, middle and end border of an axis
+fn axis(length: f32, pixels: f32, start_inset: f32, end_inset: f32, scale: f32) -> [Slice; 3] {
+    let mut start_border = start_inset * scale;
+    let mut end_border = end_inset * scale;
+    let borders = start_border + end_border;
+    if borders > length && borders > 0.0 {
+        start_border *= length / borders;
+        end_border *= length / borders;
+    }
+    let u_start = start_inset / pixels;
+    let u_end = 1.0 - end_inset / pixels;
+    [
+        Slice {
+            start: 0.0,
+            end: start_border,
+            u_start: 0.0,
+            u_end: u_start,
+            tile_length: start_inset * scale,
+        },
+        Slice {
+            start: start_border,
+            end: length - end_border,
+            u_start,
+            u_end,
+            tile_length: (pixels - start_inset - end_inset) * scale,
+        },
+        Slice {
+            start: length - end_border,
+            end: length,
+            u_start: u_end,
+            u_end: 1.0,
+            tile_length: end_inset * scale,
+        },
+    ]
+}
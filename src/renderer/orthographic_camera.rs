@@ -1,15 +1,9 @@
-use glam::{Mat4, Vec3};
-use lazy_static::lazy_static;
-
-lazy_static! {
-    #[cfg_attr(rustfmt, rustfmt_skip)]
-    static ref OPENGL_TO_WGPU_MATRIX: glam::Mat4 = glam::Mat4::from_cols_array(&[
-        1.0, 0.0, 0.0, 0.0,
-        0.0, 1.0, 0.0, 0.0,
-        0.0, 0.0, 0.5, 0.0,
-        0.0, 0.0, 0.5, 1.0
-    ]);
-}
+use super::{
+    camera::{Camera, OPENGL_TO_WGPU_MATRIX},
+    renderer_api::RendererApi,
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use mint::Point2;
 
 pub struct OrthographicCamera {
     pub projection_matrix: Mat4,
@@ -17,11 +11,25 @@ pub struct OrthographicCamera {
     pub view_matrix: Mat4,
     pub rotation: f32,
     pub position: Vec3,
+    near: f32,
+    far: f32,
 }
 
 impl OrthographicCamera {
+    /// Sees from -1 to 1 in z, use `with_clip_planes` for more depth
     pub fn new(left: f32, right: f32, bottom: f32, top: f32) -> Self {
-        let projection_matrix = glam::Mat4::orthographic_rh_gl(left, right, bottom, top, -1.0, 1.0);
+        Self::with_clip_planes(left, right, bottom, top, -1.0, 1.0)
+    }
+
+    pub fn with_clip_planes(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    ) -> Self {
+        let projection_matrix = glam::Mat4::orthographic_rh_gl(left, right, bottom, top, near, far);
         let view_matrix = glam::Mat4::identity();
         let view_projection_matrix = *OPENGL_TO_WGPU_MATRIX * projection_matrix * view_matrix;
 
@@ -31,9 +39,49 @@ impl OrthographicCamera {
             view_matrix,
             rotation: Default::default(),
             position: Default::default(),
+            near,
+            far,
         }
     }
 
+    pub fn set_projection(&mut self, left: f32, right: f32, bottom: f32, top: f32) {
+        self.projection_matrix =
+            glam::Mat4::orthographic_rh_gl(left, right, bottom, top, self.near, self.far);
+        self.view_projection_matrix =
+            *OPENGL_TO_WGPU_MATRIX * self.projection_matrix * self.view_matrix;
+    }
+
+    /// Width and height of what the camera sees in world units
+    pub fn view_size(&self) -> Vec2 {
+        Vec2::new(
+            2.0 / self.projection_matrix.x_axis().x(),
+            2.0 / self.projection_matrix.y_axis().y(),
+        )
+    }
+
+    /// The view matrix needs to be up to date, see `recalculate_view_matrix`
+    pub fn ndc_to_world(&self, ndc: Vec2) -> Vec2 {
+        let inverse = (self.projection_matrix * self.view_matrix).inverse();
+        let world = inverse * Vec4::new(ndc.x(), ndc.y(), 0.0, 1.0);
+        Vec2::new(world.x(), world.y())
+    }
+
+    pub fn world_to_ndc(&self, world: Vec2) -> Vec2 {
+        let ndc =
+            self.projection_matrix * self.view_matrix * Vec4::new(world.x(), world.y(), 0.0, 1.0);
+        Vec2::new(ndc.x(), ndc.y())
+    }
+
+    /// `position` is in physical pixels like `InputContext::mouse_position`,
+    /// the camera is expected to cover the whole window
+    pub fn screen_to_world(&self, api: &RendererApi, position: Point2<f64>) -> Vec2 {
+        self.ndc_to_world(api.screen_to_ndc(position))
+    }
+
+    pub fn world_to_screen(&self, api: &RendererApi, world: Vec2) -> Point2<f64> {
+        api.ndc_to_screen(self.world_to_ndc(world))
+    }
+
     pub fn recalculate_view_matrix(&mut self) {
         let transform_matrix = glam::Mat4::from_translation(self.position)
             * glam::Mat4::from_rotation_ypr(0.0, 0.0, self.rotation);
@@ -43,3 +91,21 @@ impl OrthographicCamera {
             *OPENGL_TO_WGPU_MATRIX * self.projection_matrix * self.view_matrix;
     }
 }
+
+impl Camera for OrthographicCamera {
+    fn view_matrix(&self) -> Mat4 {
+        self.view_matrix
+    }
+
+    fn projection_matrix(&self) -> Mat4 {
+        *OPENGL_TO_WGPU_MATRIX * self.projection_matrix
+    }
+
+    fn view_projection_matrix(&self) -> Mat4 {
+        self.view_projection_matrix
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+}
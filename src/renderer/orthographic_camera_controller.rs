@@ -0,0 +1,204 @@
+use super::orthographic_camera::OrthographicCamera;
+use crate::{
+    event::Event,
+    input::{InputContext, MouseButton, VirtualKeyCode},
+};
+use glam::{Vec2, Vec3};
+use mint::Point2;
+use std::time::Duration;
+
+/// Edges of what the camera sees, relative to its position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrthographicCameraBounds {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl OrthographicCameraBounds {
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.top - self.bottom
+    }
+}
+
+/// Keeps an `OrthographicCamera` at the aspect ratio of the window and moves it with the input
+///
+/// WASD moves, QE rotates when rotation is enabled, the mouse wheel zooms
+/// and dragging with the right or middle button pans.
+/// The zoom level is half the height of the view in world units.
+/// `Application::camera_controller` is updated by the application, a layer owning another one updates it itself.
+/// ```rust,ignore
+///     fn on_update(&mut self, app: &mut Application) {
+///         self.controller.on_update(&app.input_context, app.delta_t);
+///     }
+///     fn on_event(&mut self, app: &mut Application, event: &Event) {
+///         self.controller.on_event(event);
+///     }
+/// ```
+pub struct OrthographicCameraController {
+    pub camera: OrthographicCamera,
+    /// The camera isn't touched while disabled so something else can move it, resizes are still handled
+    pub enabled: bool,
+    pub rotation_enabled: bool,
+    /// Radians per second
+    pub rotation_speed: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Zoom level change per line scrolled
+    pub zoom_speed: f32,
+    /// How fast the camera catches up to where it's moved, 0 moves it instantly
+    pub smoothing: f32,
+    aspect_ratio: f32,
+    zoom_level: f32,
+    viewport: (u32, u32),
+    target_position: Vec3,
+    last_mouse_position: Option<Point2<f64>>,
+}
+
+impl OrthographicCameraController {
+    pub fn new(width: u32, height: u32, rotation_enabled: bool) -> Self {
+        let aspect_ratio = width.max(1) as f32 / height.max(1) as f32;
+        let zoom_level = 1.0;
+        Self {
+            camera: OrthographicCamera::new(
+                -aspect_ratio * zoom_level,
+                aspect_ratio * zoom_level,
+                -zoom_level,
+                zoom_level,
+            ),
+            enabled: true,
+            rotation_enabled,
+            rotation_speed: std::f32::consts::PI,
+            min_zoom: 0.25,
+            max_zoom: 20.0,
+            zoom_speed: 0.25,
+            smoothing: 15.0,
+            aspect_ratio,
+            zoom_level,
+            viewport: (width.max(1), height.max(1)),
+            target_position: Vec3::zero(),
+            last_mouse_position: None,
+        }
+    }
+
+    pub fn on_update(&mut self, input: &InputContext, delta_t: Duration) {
+        let dt = delta_t.as_secs_f32();
+
+        if !self.enabled {
+            self.target_position = self.camera.position;
+            self.last_mouse_position = None;
+            return;
+        }
+
+        // moving is faster when zoomed out so it feels the same on screen
+        let speed = self.zoom_level * dt;
+        let (sin, cos) = self.camera.rotation.sin_cos();
+        let right = Vec3::new(cos, sin, 0.0);
+        let up = Vec3::new(-sin, cos, 0.0);
+        if input.is_key_pressed(VirtualKeyCode::A) {
+            self.target_position -= right * speed;
+        }
+        if input.is_key_pressed(VirtualKeyCode::D) {
+            self.target_position += right * speed;
+        }
+        if input.is_key_pressed(VirtualKeyCode::W) {
+            self.target_position += up * speed;
+        }
+        if input.is_key_pressed(VirtualKeyCode::S) {
+            self.target_position -= up * speed;
+        }
+
+        if self.rotation_enabled {
+            if input.is_key_pressed(VirtualKeyCode::Q) {
+                self.camera.rotation += self.rotation_speed * dt;
+            }
+            if input.is_key_pressed(VirtualKeyCode::E) {
+                self.camera.rotation -= self.rotation_speed * dt;
+            }
+        }
+
+        let dragging = input.is_mouse_pressed(MouseButton::Right)
+            || input.is_mouse_pressed(MouseButton::Middle);
+        let mouse_position = input.mouse_position;
+        if let (true, Some(last)) = (dragging, self.last_mouse_position) {
+            // the point under the cursor follows the cursor
+            let pixel = self.bounds().height() / self.viewport.1 as f32;
+            let delta = Vec2::new(
+                (mouse_position.x - last.x) as f32,
+                (mouse_position.y - last.y) as f32,
+            ) * pixel;
+            self.target_position -= right * delta.x() - up * delta.y();
+        }
+        self.last_mouse_position = if dragging { Some(mouse_position) } else { None };
+
+        self.camera.position = if self.smoothing > 0.0 {
+            let t = 1.0 - (-self.smoothing * dt).exp();
+            self.camera.position + (self.target_position - self.camera.position) * t
+        } else {
+            self.target_position
+        };
+        self.camera.recalculate_view_matrix();
+    }
+
+    /// Handles the mouse wheel zoom and window resizes
+    pub fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::MouseScrolled(delta) if self.enabled => {
+                self.set_zoom_level(self.zoom_level - delta.y * self.zoom_speed);
+            }
+            Event::WindowResize(width, height) => self.on_resize(*width, *height),
+            _ => {}
+        }
+    }
+
+    /// A zero size, like a minimized window, keeps the previous aspect ratio
+    pub fn on_resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.viewport = (width, height);
+        self.aspect_ratio = width as f32 / height as f32;
+        self.update_projection();
+    }
+
+    pub fn zoom_level(&self) -> f32 {
+        self.zoom_level
+    }
+
+    /// Clamped between `min_zoom` and `max_zoom`
+    pub fn set_zoom_level(&mut self, zoom_level: f32) {
+        self.zoom_level = zoom_level.max(self.min_zoom).min(self.max_zoom);
+        self.update_projection();
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    /// Moves the camera there without smoothing
+    pub fn set_position(&mut self, position: Vec3) {
+        self.target_position = position;
+        self.camera.position = position;
+        self.camera.recalculate_view_matrix();
+    }
+
+    pub fn bounds(&self) -> OrthographicCameraBounds {
+        OrthographicCameraBounds {
+            left: -self.aspect_ratio * self.zoom_level,
+            right: self.aspect_ratio * self.zoom_level,
+            bottom: -self.zoom_level,
+            top: self.zoom_level,
+        }
+    }
+
+    fn update_projection(&mut self) {
+        let bounds = self.bounds();
+        self.camera
+            .set_projection(bounds.left, bounds.right, bounds.bottom, bounds.top);
+    }
+}
@@ -0,0 +1,447 @@
+use super::{
+    renderer_2d::Renderer2D, renderer_api::RendererApi, sub_texture::SubTexture2D, texture::Texture,
+};
+use anyhow::{anyhow, Context, Result};
+use glam::{Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+/// Values that can be interpolated by a `Curve`
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 4] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let mut value = self;
+        for (value, other) in value.iter_mut().zip(other.iter()) {
+            *value = value.lerp(*other, t);
+        }
+        value
+    }
+}
+
+/// Keys of a value over the lifetime of a particle, from 0 when it spawns to 1 when it dies
+///
+/// Values between the keys are interpolated linearly, the keys are sorted by time.
+/// ```json
+///     "size": [[0.0, 0.5], [0.2, 1.0], [1.0, 0.0]]
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Curve<T> {
+    pub keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub fn new(keys: Vec<(f32, T)>) -> Self {
+        let mut curve = Self { keys };
+        curve.sort();
+        curve
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+
+    /// `None` without keys
+    pub fn sample(&self, t: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        if t <= first.0 {
+            return Some(first.1);
+        }
+        for keys in self.keys.windows(2) {
+            let ((start, from), (end, to)) = (keys[0], keys[1]);
+            if t <= end {
+                let length = end - start;
+                let t = if length > 0.0 {
+                    (t - start) / length
+                } else {
+                    1.0
+                };
+                return Some(from.lerp(to, t));
+            }
+        }
+        self.keys.last().map(|(_, value)| *value)
+    }
+
+    /// Needed after editing the times of the keys
+    pub fn sort(&mut self) {
+        self.keys
+            .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    }
+}
+
+/// Particles spawned at once, `time` is in seconds from the start of the cycle
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Burst {
+    pub time: f32,
+    pub count: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulationSpace {
+    /// Particles stay where they spawned when the emitter moves
+    World,
+    /// Particles follow the position and rotation of the emitter
+    Local,
+}
+
+/// Everything describing an emitter, saved and loaded as json
+///
+/// Ranges are `[min, max]` and a random value is picked in them for every particle. Angles are
+/// in degrees, counter clockwise from the x axis. Missing fields keep their default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmitterDefinition {
+    /// Particles per second
+    pub spawn_rate: f32,
+    pub bursts: Vec<Burst>,
+    /// Seconds of a cycle of the bursts, 0 to never stop the spawn rate and ignore the bursts
+    pub duration: f32,
+    pub looping: bool,
+    pub max_particles: u32,
+    /// Seconds
+    pub lifetime: [f32; 2],
+    /// Distance from the emitter particles spawn at, in a random direction
+    pub spawn_radius: f32,
+    pub direction: f32,
+    /// Angle of the cone around `direction` particles are launched in
+    pub spread: f32,
+    pub speed: [f32; 2],
+    /// Added to the velocity every second, in the simulation space
+    pub acceleration: [f32; 2],
+    /// Multiplied by `size_over_lifetime`
+    pub size: [f32; 2],
+    pub size_over_lifetime: Curve<f32>,
+    pub color_over_lifetime: Curve<[f32; 4]>,
+    pub rotation: [f32; 2],
+    /// Degrees per second
+    pub angular_velocity: [f32; 2],
+    pub space: SimulationSpace,
+    /// Image of the particles, relative to the definition file, quads are drawn without it
+    pub texture: Option<String>,
+}
+
+impl Default for EmitterDefinition {
+    fn default() -> Self {
+        Self {
+            spawn_rate: 50.0,
+            bursts: vec![],
+            duration: 0.0,
+            looping: true,
+            max_particles: 1000,
+            lifetime: [1.0, 1.5],
+            spawn_radius: 0.0,
+            direction: 90.0,
+            spread: 30.0,
+            speed: [1.0, 1.5],
+            acceleration: [0.0, -1.0],
+            size: [0.05, 0.1],
+            size_over_lifetime: Curve::new(vec![(0.0, 1.0), (1.0, 0.0)]),
+            color_over_lifetime: Curve::new(vec![
+                (0.0, [1.0, 0.8, 0.2, 1.0]),
+                (1.0, [1.0, 0.2, 0.1, 0.0]),
+            ]),
+            rotation: [0.0, 0.0],
+            angular_velocity: [0.0, 0.0],
+            space: SimulationSpace::World,
+            texture: None,
+        }
+    }
+}
+
+impl EmitterDefinition {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut definition: Self = serde_json::from_str(json)?;
+        definition.size_over_lifetime.sort();
+        definition.color_over_lifetime.sort();
+        Ok(definition)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    /// In the simulation space
+    position: Vec2,
+    velocity: Vec2,
+    /// Radians
+    rotation: f32,
+    angular_velocity: f32,
+    size: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Spawns, simulates and draws particles on the CPU
+///
+/// ```rust,ignore
+///     let mut fire = ParticleEmitter::from_file(&mut app.renderer.api, "assets/particles/fire.json")?;
+///     // on_update
+///     fire.position = torch_position;
+///     fire.update(app.delta_t);
+///     // on_render
+///     fire.draw(&mut app.renderer_2d);
+///     // on_imgui_render
+///     particle_emitter_editor(ui, &mut app.renderer.api, "Fire", &mut fire);
+/// ```
+pub struct ParticleEmitter {
+    pub definition: EmitterDefinition,
+    pub position: Vec3,
+    /// Radians, counter clockwise
+    pub rotation: f32,
+    /// The whole sprite is drawn when the definition has a texture
+    pub sprite: Option<SubTexture2D>,
+    /// New particles are spawned while playing
+    pub playing: bool,
+    path: Option<PathBuf>,
+    particles: Vec<Particle>,
+    /// Seconds since the start of the cycle
+    time: f32,
+    /// Fraction of a particle left from the previous updates
+    spawn_accumulator: f32,
+    rng: Rng,
+}
+
+impl ParticleEmitter {
+    pub fn new(definition: EmitterDefinition) -> Self {
+        Self {
+            definition,
+            position: Vec3::zero(),
+            rotation: 0.0,
+            sprite: None,
+            playing: true,
+            path: None,
+            particles: vec![],
+            time: 0.0,
+            spawn_accumulator: 0.0,
+            rng: Rng::new(),
+        }
+    }
+
+    /// Loads the definition and its texture, the emitter can then be reloaded and saved
+    pub fn from_file<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let mut emitter = Self::new(EmitterDefinition::default());
+        emitter.path = Some(path.as_ref().to_path_buf());
+        emitter.reload(api)?;
+        Ok(emitter)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Reads the definition file again, the particles alive are kept
+    pub fn reload(&mut self, api: &mut RendererApi) -> Result<()> {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read particle emitter {:?}", path))?;
+        let definition = EmitterDefinition::from_json(&json)
+            .with_context(|| format!("Failed to parse particle emitter {:?}", path))?;
+        self.sprite = match definition.texture.as_ref() {
+            Some(texture) => {
+                let texture_path = path.parent().unwrap_or_else(|| Path::new("")).join(texture);
+                let texture = Texture::from_file(api, &texture_path).with_context(|| {
+                    format!("Failed to load particle texture {:?}", texture_path)
+                })?;
+                Some(SubTexture2D::from_texture(Rc::new(texture)))
+            }
+            None => None,
+        };
+        self.definition = definition;
+        Ok(())
+    }
+
+    /// Writes the definition to `path`, which is then used by `reload` and `save`
+    pub fn save_as<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.definition.to_json()?)
+            .with_context(|| format!("Failed to save particle emitter {:?}", path))?;
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Writes the definition back to the file it was loaded from
+    pub fn save(&mut self) -> Result<()> {
+        match self.path.clone() {
+            Some(path) => self.save_as(path),
+            None => Err(anyhow!("The particle emitter has no file")),
+        }
+    }
+
+    /// Starts the cycle over and plays it, the particles alive are kept
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.spawn_accumulator = 0.0;
+        self.playing = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Spawns `count` particles now, even when not playing
+    pub fn emit(&mut self, count: u32) {
+        let available =
+            (self.definition.max_particles as usize).saturating_sub(self.particles.len());
+        for _ in 0..(count as usize).min(available) {
+            let particle = self.spawn();
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn update(&mut self, delta_t: Duration) {
+        let delta_t = delta_t.as_secs_f32();
+        let acceleration = Vec2::from(self.definition.acceleration);
+        for particle in self.particles.iter_mut() {
+            particle.age += delta_t;
+            particle.velocity += acceleration * delta_t;
+            particle.position += particle.velocity * delta_t;
+            particle.rotation += particle.angular_velocity * delta_t;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        if !self.playing {
+            return;
+        }
+        self.spawn_accumulator += self.definition.spawn_rate.max(0.0) * delta_t;
+        let count = self.spawn_accumulator.floor();
+        self.spawn_accumulator -= count;
+        self.emit(count as u32);
+
+        let duration = self.definition.duration;
+        if duration <= 0.0 {
+            return;
+        }
+        // the bursts of every cycle crossed by this update
+        let mut from = self.time;
+        let mut to = self.time + delta_t;
+        loop {
+            let bursts = self
+                .definition
+                .bursts
+                .iter()
+                .filter(|burst| burst.time >= from && burst.time < to.min(duration))
+                .map(|burst| burst.count)
+                .collect::<Vec<_>>();
+            for count in bursts {
+                self.emit(count);
+            }
+            if to < duration {
+                break;
+            }
+            if !self.definition.looping {
+                self.playing = false;
+                break;
+            }
+            from = 0.0;
+            to -= duration;
+        }
+        self.time = to;
+    }
+
+    /// Every particle is a quad at the z of the emitter, in the order they spawned
+    pub fn draw(&self, renderer: &mut Renderer2D) {
+        let definition = &self.definition;
+        let (sin, cos) = self.rotation.sin_cos();
+        for particle in self.particles.iter() {
+            let t = particle.age / particle.lifetime;
+            let size = particle.size * definition.size_over_lifetime.sample(t).unwrap_or(1.0);
+            let color = Vec4::from(definition.color_over_lifetime.sample(t).unwrap_or([1.0; 4]));
+            let (position, rotation) = match definition.space {
+                SimulationSpace::World => (particle.position, particle.rotation),
+                SimulationSpace::Local => {
+                    let p = particle.position;
+                    (
+                        Vec2::new(p.x() * cos - p.y() * sin, p.x() * sin + p.y() * cos)
+                            + self.position.truncate(),
+                        particle.rotation + self.rotation,
+                    )
+                }
+            };
+            let position = position.extend(self.position.z());
+            let size = Vec2::new(size, size);
+            match self.sprite.as_ref() {
+                Some(sprite) => {
+                    renderer.draw_rotated_sprite(position, size, rotation, sprite, color)
+                }
+                None => renderer.draw_rotated_quad(position, size, rotation, color),
+            }
+        }
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let definition = &self.definition;
+        let rng = &mut self.rng;
+        let direction = (definition.direction
+            + rng.range([-definition.spread / 2.0, definition.spread / 2.0]))
+        .to_radians();
+        let speed = rng.range(definition.speed);
+        let offset_angle = rng.range([0.0, std::f32::consts::PI * 2.0]);
+        // uniform over the disk
+        let offset_length = definition.spawn_radius * rng.range([0.0, 1.0]).sqrt();
+        let mut particle = Particle {
+            position: Vec2::new(offset_angle.cos(), offset_angle.sin()) * offset_length,
+            velocity: Vec2::new(direction.cos(), direction.sin()) * speed,
+            rotation: rng.range(definition.rotation).to_radians(),
+            angular_velocity: rng.range(definition.angular_velocity).to_radians(),
+            size: rng.range(definition.size),
+            age: 0.0,
+            lifetime: rng.range(definition.lifetime).max(std::f32::EPSILON),
+        };
+        if definition.space == SimulationSpace::World {
+            let (sin, cos) = self.rotation.sin_cos();
+            let rotate = |v: Vec2| Vec2::new(v.x() * cos - v.y() * sin, v.x() * sin + v.y() * cos);
+            particle.position = rotate(particle.position) + self.position.truncate();
+            particle.velocity = rotate(particle.velocity);
+            particle.rotation += self.rotation;
+        }
+        particle
+    }
+}
+
+/// xorshift, good enough for particles
+struct Rng(u32);
+
+impl Rng {
+    fn new() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos());
+        Self(seed | 1)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, range: [f32; 2]) -> f32 {
+        range[0] + (range[1] - range[0]) * self.next_f32()
+    }
+}
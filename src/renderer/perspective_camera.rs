@@ -0,0 +1,146 @@
+use super::{
+    camera::{Camera, OPENGL_TO_WGPU_MATRIX},
+    renderer_api::RendererApi,
+};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use mint::Point2;
+
+/// A 3D camera looking down -z when its orientation is the identity
+pub struct PerspectiveCamera {
+    pub projection_matrix: Mat4,
+    pub view_projection_matrix: Mat4,
+    pub view_matrix: Mat4,
+    pub position: Vec3,
+    pub orientation: Quat,
+    /// Vertical field of view in radians
+    fov_y: f32,
+    aspect_ratio: f32,
+    near: f32,
+    far: f32,
+}
+
+impl PerspectiveCamera {
+    pub fn new(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let mut camera = Self {
+            projection_matrix: Mat4::identity(),
+            view_projection_matrix: Mat4::identity(),
+            view_matrix: Mat4::identity(),
+            position: Vec3::zero(),
+            orientation: Quat::identity(),
+            fov_y,
+            aspect_ratio,
+            near,
+            far,
+        };
+        camera.set_projection(fov_y, aspect_ratio, near, far);
+        camera
+    }
+
+    pub fn set_projection(&mut self, fov_y: f32, aspect_ratio: f32, near: f32, far: f32) {
+        self.fov_y = fov_y;
+        self.aspect_ratio = aspect_ratio;
+        self.near = near;
+        self.far = far;
+        self.projection_matrix = Mat4::perspective_rh_gl(fov_y, aspect_ratio, near, far);
+        self.view_projection_matrix =
+            *OPENGL_TO_WGPU_MATRIX * self.projection_matrix * self.view_matrix;
+    }
+
+    /// Keeps the field of view, a zero size is ignored
+    pub fn on_resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.set_projection(
+            self.fov_y,
+            width as f32 / height as f32,
+            self.near,
+            self.far,
+        );
+    }
+
+    pub fn fov_y(&self) -> f32 {
+        self.fov_y
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * -Vec3::unit_z()
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::unit_x()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::unit_y()
+    }
+
+    /// Turns the camera towards `target`, `up` can't be parallel to the direction
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let view = Mat4::look_at_rh(self.position, target, up);
+        self.orientation = Quat::from_rotation_mat4(&view.inverse());
+        self.recalculate_view_matrix();
+    }
+
+    /// Ray going from the camera through a point of the screen, in physical pixels.
+    /// Returns the origin on the near plane and the normalized direction
+    pub fn screen_to_ray(&self, api: &RendererApi, position: Point2<f64>) -> (Vec3, Vec3) {
+        let ndc = api.screen_to_ndc(position);
+        let inverse = self.view_projection_matrix.inverse();
+        let unproject = |depth: f32| {
+            let point = inverse * Vec4::new(ndc.x(), ndc.y(), depth, 1.0);
+            point.truncate() / point.w()
+        };
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        (near, (far - near).normalize())
+    }
+
+    /// `None` when the point is behind the camera
+    pub fn world_to_screen(&self, api: &RendererApi, world: Vec3) -> Option<Point2<f64>> {
+        let clip = self.view_projection_matrix * world.extend(1.0);
+        if clip.w() <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w();
+        Some(api.ndc_to_screen(glam::Vec2::new(ndc.x(), ndc.y())))
+    }
+
+    pub fn recalculate_view_matrix(&mut self) {
+        let transform_matrix = Mat4::from_rotation_translation(self.orientation, self.position);
+
+        self.view_matrix = transform_matrix.inverse();
+        self.view_projection_matrix =
+            *OPENGL_TO_WGPU_MATRIX * self.projection_matrix * self.view_matrix;
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn view_matrix(&self) -> Mat4 {
+        self.view_matrix
+    }
+
+    fn projection_matrix(&self) -> Mat4 {
+        *OPENGL_TO_WGPU_MATRIX * self.projection_matrix
+    }
+
+    fn view_projection_matrix(&self) -> Mat4 {
+        self.view_projection_matrix
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+}
@@ -1,50 +1,252 @@
 use super::{
-    buffer::VertexBufferLayout, primitives::VertexArray, renderer_api::RendererApi, shader::Shader,
+    primitives::VertexArray, renderer_api::RendererApi, shader::Shader, texture::DEPTH_FORMAT,
 };
-use crate::Application;
 
-pub struct Pipeline<T> {
+/// Depth test of a pipeline
+///
+/// With the orthographic camera a bigger z is closer to the camera,
+/// so with the default state sprites with a bigger z are drawn on top.
+/// Sprites with the same z are drawn in submission order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthState {
+    pub compare: wgpu::CompareFunction,
+    pub write: bool,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            compare: wgpu::CompareFunction::LessEqual,
+            write: true,
+        }
+    }
+}
+
+impl DepthState {
+    /// Always drawn on top of what's already there, useful for transparent objects and overlays
+    pub fn disabled() -> Self {
+        Self {
+            compare: wgpu::CompareFunction::Always,
+            write: false,
+        }
+    }
+
+    /// Tested against the depth buffer without writing to it
+    pub fn read_only() -> Self {
+        Self {
+            write: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn descriptor(&self) -> wgpu::DepthStencilStateDescriptor {
+        wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: self.write,
+            depth_compare: self.compare,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        }
+    }
+}
+
+/// How the output of the fragment shader is combined with what's already in the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrites the target, alpha is ignored
+    Replace,
+    /// Classic transparency, `src * src_alpha + dst * (1 - src_alpha)`
+    Alpha,
+    /// For colors that were already multiplied by their alpha, `src + dst * (1 - src_alpha)`
+    Premultiplied,
+    /// Brightens the target, useful for glows and particles, `src * src_alpha + dst`
+    Additive,
+    /// Darkens the target, useful for shadows, `src * dst`
+    Multiply,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Replace
+    }
+}
+
+impl BlendMode {
+    pub fn color_blend(&self) -> wgpu::BlendDescriptor {
+        use wgpu::BlendFactor::*;
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Replace => return wgpu::BlendDescriptor::REPLACE,
+            BlendMode::Alpha => (SrcAlpha, OneMinusSrcAlpha),
+            BlendMode::Premultiplied => (One, OneMinusSrcAlpha),
+            BlendMode::Additive => (SrcAlpha, One),
+            BlendMode::Multiply => (DstColor, Zero),
+        };
+        wgpu::BlendDescriptor {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+
+    /// The alpha of the target accumulates coverage, except for multiply which leaves it untouched
+    pub fn alpha_blend(&self) -> wgpu::BlendDescriptor {
+        use wgpu::BlendFactor::*;
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Replace => return wgpu::BlendDescriptor::REPLACE,
+            BlendMode::Alpha | BlendMode::Premultiplied => (One, OneMinusSrcAlpha),
+            BlendMode::Additive => (One, One),
+            BlendMode::Multiply => (Zero, One),
+        };
+        wgpu::BlendDescriptor {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+}
+
+/// Fixed function state of a pipeline
+///
+/// The presets cover the usual cases, the fields can be changed from there.
+/// wgpu 0.5 has no polygon mode, triangles are always filled. Lines and points are drawn with
+/// the `lines` and `points` topologies.
+/// ```rust,ignore
+///     material.render_state = RenderState {
+///         cull_mode: wgpu::CullMode::None,
+///         ..RenderState::transparent()
+///     };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    pub cull_mode: wgpu::CullMode,
+    pub front_face: wgpu::FrontFace,
+    pub topology: wgpu::PrimitiveTopology,
+    pub depth: DepthState,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self::opaque()
+    }
+}
+
+impl RenderState {
+    /// Back faces are culled and the depth is tested and written
+    pub fn opaque() -> Self {
+        Self {
+            blend: BlendMode::Replace,
+            cull_mode: wgpu::CullMode::Back,
+            front_face: wgpu::FrontFace::Ccw,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            depth: DepthState::default(),
+        }
+    }
+
+    /// Alpha blended, the depth is tested but not written so what's behind stays visible.
+    /// Transparent draws need to be sorted back to front, which `SortMode::Depth` does
+    pub fn transparent() -> Self {
+        Self {
+            blend: BlendMode::Alpha,
+            cull_mode: wgpu::CullMode::None,
+            depth: DepthState::read_only(),
+            ..Self::opaque()
+        }
+    }
+
+    pub fn premultiplied() -> Self {
+        Self {
+            blend: BlendMode::Premultiplied,
+            ..Self::transparent()
+        }
+    }
+
+    pub fn additive() -> Self {
+        Self {
+            blend: BlendMode::Additive,
+            ..Self::transparent()
+        }
+    }
+
+    pub fn multiply() -> Self {
+        Self {
+            blend: BlendMode::Multiply,
+            ..Self::transparent()
+        }
+    }
+
+    /// Each pair of vertices is a line, or each consecutive vertex with `strip`
+    pub fn lines(strip: bool) -> Self {
+        Self {
+            topology: if strip {
+                wgpu::PrimitiveTopology::LineStrip
+            } else {
+                wgpu::PrimitiveTopology::LineList
+            },
+            ..Self::transparent()
+        }
+    }
+
+    pub fn points() -> Self {
+        Self {
+            topology: wgpu::PrimitiveTopology::PointList,
+            ..Self::transparent()
+        }
+    }
+
+    /// Covers the whole target without testing the depth, used by the post processing passes
+    pub fn full_screen() -> Self {
+        Self {
+            cull_mode: wgpu::CullMode::None,
+            depth: DepthState::disabled(),
+            ..Self::opaque()
+        }
+    }
+
+    pub fn rasterization_state(&self) -> wgpu::RasterizationStateDescriptor {
+        wgpu::RasterizationStateDescriptor {
+            front_face: self.front_face,
+            cull_mode: self.cull_mode,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }
+    }
+}
+
+pub struct Pipeline {
     pub render_pipeline: wgpu::RenderPipeline,
-    pub vertex_array: VertexArray<T>,
+    pub vertex_array: VertexArray,
 }
 
-impl<T> Pipeline<T>
-where
-    T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable,
-{
-    pub fn new(renderer: &RendererApi, shader: &Shader, vertex_array: VertexArray<T>) -> Self {
+impl Pipeline {
+    /// Draws to the frame, needs to be recreated when the sample count changes
+    pub fn new(renderer: &RendererApi, shader: &Shader, vertex_array: VertexArray) -> Self {
+        Self::with_state(renderer, shader, vertex_array, RenderState::default())
+    }
+
+    pub fn with_state(
+        renderer: &RendererApi,
+        shader: &Shader,
+        vertex_array: VertexArray,
+        state: RenderState,
+    ) -> Self {
         Self {
-            render_pipeline: shader.create_pipeline(renderer, &vertex_array, 1),
+            render_pipeline: shader.create_pipeline(
+                renderer,
+                &vertex_array,
+                &renderer.frame_format(),
+                state,
+            ),
             vertex_array,
         }
     }
 
-    pub fn draw(&self, app: &mut Application, frame: &wgpu::SwapChainOutput) {
-        let mut render_pass =
-            app.renderer
-                .api
-                .encoder
-                .begin_render_pass(&wgpu::RenderPassDescriptor {
-                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                        attachment: &frame.view,
-                        resolve_target: None,
-                        load_op: wgpu::LoadOp::Load,
-                        store_op: wgpu::StoreOp::Store,
-                        clear_color: wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 0.0,
-                        },
-                    }],
-                    depth_stencil_attachment: None,
-                });
-
+    /// Records the draw in a pass started with `RendererApi::begin_render_pass`
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
         render_pass.set_pipeline(&self.render_pipeline);
-        // TODO
-        // render_pass.set_bind_group(0, bind_group, offsets)
-        render_pass.set_vertex_buffer(0, &self.vertex_array.vertex_buffer.buffer, 0, 0);
-        render_pass.set_index_buffer(&self.vertex_array.index_buffer.buffer, 0, 0);
-        render_pass.draw_indexed(0..self.vertex_array.index_buffer.count, 0, 0..1);
+        self.vertex_array.draw(render_pass);
     }
 }
@@ -0,0 +1,514 @@
+use super::{
+    framebuffer::{Framebuffer, FramebufferSpec, RenderTarget},
+    material::{Material, UniformValue},
+    pipeline::RenderState,
+    primitives::{VertexArray, VertexPos},
+    render_pass::PassLoad,
+    renderer_api::RendererApi,
+    shader::CompileOptions,
+    shader_cache::ShaderCache,
+    texture::Texture,
+    Renderer,
+};
+use crate::Frame;
+use anyhow::{anyhow, Result};
+use glam::{Mat4, Vec2};
+use std::rc::Rc;
+
+/// The scene is rendered in hdr so tone mapping has something to map
+const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Cells per channel of the identity lut
+const LUT_SIZE: u32 = 16;
+
+/// A full screen effect and its parameters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    /// Maps hdr colors to the displayable range with the ACES curve
+    ToneMapping {
+        exposure: f32,
+    },
+    /// Blurs the parts brighter than the threshold and adds them back
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+    },
+    Vignette {
+        intensity: f32,
+        smoothness: f32,
+    },
+    /// Looks up the colors in the lut set with `PostProcessStack::set_lut`
+    ColorGrading {
+        strength: f32,
+    },
+    Fxaa,
+    Crt {
+        curvature: f32,
+        scanline_intensity: f32,
+        scanline_count: f32,
+    },
+    Pixelate {
+        pixel_size: f32,
+    },
+}
+
+impl Effect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::ToneMapping { .. } => "Tone mapping",
+            Effect::Bloom { .. } => "Bloom",
+            Effect::Vignette { .. } => "Vignette",
+            Effect::ColorGrading { .. } => "Color grading",
+            Effect::Fxaa => "FXAA",
+            Effect::Crt { .. } => "CRT",
+            Effect::Pixelate { .. } => "Pixelate",
+        }
+    }
+}
+
+pub struct PostEffect {
+    pub effect: Effect,
+    pub enabled: bool,
+    /// Bloom uses one material per pass, every other effect has a single one
+    materials: Vec<Material>,
+}
+
+/// Full screen effects applied to the scene before it's composited in the frame
+///
+/// Layers draw the scene to `target`, the stack is applied after the layers and before the overlays
+/// so the ui isn't affected. Effects are applied in order, the disabled ones are skipped.
+/// ```rust,ignore
+///     let target = app.post_process.as_ref().map_or(frame.into(), |stack| stack.target(frame));
+///     let mut scene = app.renderer.begin_scene(&camera);
+///     scene.submit(&material, &vertex_array, transform, target);
+/// ```
+pub struct PostProcessStack {
+    pub enabled: bool,
+    pub effects: Vec<PostEffect>,
+    scene: Framebuffer,
+    ping_pong: [Framebuffer; 2],
+    /// Half resolution, bloom is blurred back and forth between them
+    bloom: [Framebuffer; 2],
+    blit: Material,
+    fullscreen_triangle: VertexArray,
+    lut: Rc<Texture>,
+}
+
+impl PostProcessStack {
+    /// Tone mapping and FXAA are enabled, the other effects are there to be toggled
+    pub fn new(api: &mut RendererApi, shader_cache: &ShaderCache) -> Result<Self> {
+        let (width, height) = (api.sc_desc.width.max(1), api.sc_desc.height.max(1));
+        let target = |width, height, depth, sample_count| {
+            Framebuffer::new(
+                &api.device,
+                FramebufferSpec {
+                    width,
+                    height,
+                    color_formats: vec![HDR_FORMAT],
+                    depth,
+                    sample_count,
+                },
+            )
+        };
+        // the scene is multisampled like the frame, the effects sample its resolved attachment
+        let scene = target(width, height, true, api.sample_count());
+        let ping_pong = [
+            target(width, height, false, 1),
+            target(width, height, false, 1),
+        ];
+        let bloom = [
+            target((width / 2).max(1), (height / 2).max(1), false, 1),
+            target((width / 2).max(1), (height / 2).max(1), false, 1),
+        ];
+
+        let lut = Rc::new(identity_lut(api));
+        let input = scene.color_attachment(0);
+        let fragment = |src: &str,
+                        uniforms: &[(&str, UniformValue)],
+                        textures: &[(&str, Rc<Texture>)]|
+         -> Result<Material> {
+            let shader = shader_cache.load(
+                include_str!("../assets/shaders/post/fullscreen.vert.glsl"),
+                src,
+                &CompileOptions::default(),
+            )?;
+            let mut material = Material::new(api, Rc::new(shader), uniforms, textures);
+            material.render_state = RenderState::full_screen();
+            Ok(material)
+        };
+        let with_input =
+            |name: &'static str| -> [(&'static str, Rc<Texture>); 1] { [(name, input.clone())] };
+
+        let blit = fragment(
+            include_str!("../assets/shaders/post/blit.frag.glsl"),
+            &[],
+            &with_input("t_Input"),
+        )?;
+
+        let effects = vec![
+            PostEffect {
+                effect: Effect::Bloom {
+                    threshold: 1.0,
+                    intensity: 0.8,
+                    radius: 1.0,
+                },
+                enabled: false,
+                materials: vec![
+                    fragment(
+                        include_str!("../assets/shaders/post/bloom_threshold.frag.glsl"),
+                        &[("u_Threshold", UniformValue::Float(1.0))],
+                        &with_input("t_Input"),
+                    )?,
+                    fragment(
+                        include_str!("../assets/shaders/post/blur.frag.glsl"),
+                        &[("u_Direction", UniformValue::Vec2(Vec2::zero()))],
+                        &with_input("t_Input"),
+                    )?,
+                    fragment(
+                        include_str!("../assets/shaders/post/blur.frag.glsl"),
+                        &[("u_Direction", UniformValue::Vec2(Vec2::zero()))],
+                        &with_input("t_Input"),
+                    )?,
+                    fragment(
+                        include_str!("../assets/shaders/post/bloom_combine.frag.glsl"),
+                        &[("u_Intensity", UniformValue::Float(0.8))],
+                        &[("t_Input", input.clone()), ("t_Bloom", input.clone())],
+                    )?,
+                ],
+            },
+            PostEffect {
+                effect: Effect::ToneMapping { exposure: 1.0 },
+                enabled: true,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/tone_mapping.frag.glsl"),
+                    &[("u_Exposure", UniformValue::Float(1.0))],
+                    &with_input("t_Input"),
+                )?],
+            },
+            PostEffect {
+                effect: Effect::ColorGrading { strength: 1.0 },
+                enabled: false,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/color_grading.frag.glsl"),
+                    &[
+                        ("u_Strength", UniformValue::Float(1.0)),
+                        ("u_LutSize", UniformValue::Float(LUT_SIZE as f32)),
+                    ],
+                    &[("t_Input", input.clone()), ("t_Lut", lut.clone())],
+                )?],
+            },
+            PostEffect {
+                effect: Effect::Vignette {
+                    intensity: 0.5,
+                    smoothness: 0.5,
+                },
+                enabled: false,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/vignette.frag.glsl"),
+                    &[
+                        ("u_Intensity", UniformValue::Float(0.5)),
+                        ("u_Smoothness", UniformValue::Float(0.5)),
+                    ],
+                    &with_input("t_Input"),
+                )?],
+            },
+            PostEffect {
+                effect: Effect::Crt {
+                    curvature: 0.1,
+                    scanline_intensity: 0.3,
+                    scanline_count: 400.0,
+                },
+                enabled: false,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/crt.frag.glsl"),
+                    &[
+                        ("u_Curvature", UniformValue::Float(0.1)),
+                        ("u_ScanlineIntensity", UniformValue::Float(0.3)),
+                        ("u_ScanlineCount", UniformValue::Float(400.0)),
+                    ],
+                    &with_input("t_Input"),
+                )?],
+            },
+            PostEffect {
+                effect: Effect::Pixelate { pixel_size: 4.0 },
+                enabled: false,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/pixelate.frag.glsl"),
+                    &[
+                        ("u_Resolution", UniformValue::Vec2(Vec2::zero())),
+                        ("u_PixelSize", UniformValue::Float(4.0)),
+                    ],
+                    &with_input("t_Input"),
+                )?],
+            },
+            PostEffect {
+                effect: Effect::Fxaa,
+                enabled: true,
+                materials: vec![fragment(
+                    include_str!("../assets/shaders/post/fxaa.frag.glsl"),
+                    &[("u_TexelSize", UniformValue::Vec2(Vec2::zero()))],
+                    &with_input("t_Input"),
+                )?],
+            },
+        ];
+
+        // a triangle covering the whole screen, the parts outside are clipped
+        let fullscreen_triangle = VertexArray::create_non_indexed(
+            &api.device,
+            &[
+                VertexPos {
+                    position: [-1.0, -1.0, 0.0],
+                },
+                VertexPos {
+                    position: [3.0, -1.0, 0.0],
+                },
+                VertexPos {
+                    position: [-1.0, 3.0, 0.0],
+                },
+            ],
+        );
+
+        Ok(Self {
+            enabled: true,
+            effects,
+            scene,
+            ping_pong,
+            bloom,
+            blit,
+            fullscreen_triangle,
+            lut,
+        })
+    }
+
+    /// Follows the size and the sample count of the frame, called before the layers draw the scene
+    pub fn update_targets(&mut self, api: &RendererApi) {
+        let (width, height) = (api.sc_desc.width, api.sc_desc.height);
+        let device = &api.device;
+        self.scene.resize(device, width, height);
+        self.scene.set_sample_count(device, api.sample_count());
+        for target in self.ping_pong.iter_mut() {
+            target.resize(device, width, height);
+        }
+        for target in self.bloom.iter_mut() {
+            target.resize(device, (width / 2).max(1), (height / 2).max(1));
+        }
+    }
+
+    /// Where the scene needs to be drawn, the frame when the stack is disabled
+    pub fn target<'a>(&'a self, frame: &'a Frame) -> RenderTarget<'a> {
+        if self.enabled {
+            RenderTarget::Framebuffer(&self.scene)
+        } else {
+            RenderTarget::Frame(frame)
+        }
+    }
+
+    /// Lut used by color grading, a strip of `size` slices of `size`x`size` with blue going from
+    /// left to right. The size is the height of the texture, 16, 32 and 64 are common
+    pub fn set_lut(&mut self, lut: Rc<Texture>) -> Result<()> {
+        if lut.height < 2 || lut.width != lut.height * lut.height {
+            return Err(anyhow!(
+                "A lut of {}x{} isn't a strip of square slices, its width needs to be its height squared",
+                lut.width,
+                lut.height
+            ));
+        }
+        self.lut = lut;
+        Ok(())
+    }
+
+    /// Applies every enabled effect to the scene and writes the result to the frame
+    pub fn apply(&mut self, renderer: &mut Renderer, frame: &Frame) {
+        if !self.enabled {
+            return;
+        }
+
+        let (width, height) = (self.scene.width(), self.scene.height());
+        let lut_size = self.lut.height;
+
+        let enabled = self
+            .effects
+            .iter()
+            .enumerate()
+            .filter(|(_, effect)| effect.enabled)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if enabled.is_empty() {
+            set_texture(
+                &renderer.api,
+                &mut self.blit,
+                "t_Input",
+                self.scene.color_attachment(0),
+            );
+            draw(
+                renderer,
+                &self.blit,
+                &self.fullscreen_triangle,
+                RenderTarget::Frame(frame),
+            );
+            return;
+        }
+
+        let mut input = self.scene.color_attachment(0);
+        for (position, &index) in enabled.iter().enumerate() {
+            let output = if position + 1 == enabled.len() {
+                RenderTarget::Frame(frame)
+            } else {
+                RenderTarget::Framebuffer(&self.ping_pong[position % 2])
+            };
+
+            let effect = &mut self.effects[index];
+            update_uniforms(effect, width, height, lut_size);
+            let materials = &mut effect.materials;
+
+            if let Effect::Bloom { .. } = effect.effect {
+                let [bright, blurred] = &self.bloom;
+                set_texture(&renderer.api, &mut materials[0], "t_Input", input.clone());
+                set_texture(
+                    &renderer.api,
+                    &mut materials[1],
+                    "t_Input",
+                    bright.color_attachment(0),
+                );
+                set_texture(
+                    &renderer.api,
+                    &mut materials[2],
+                    "t_Input",
+                    blurred.color_attachment(0),
+                );
+                set_texture(&renderer.api, &mut materials[3], "t_Input", input.clone());
+                set_texture(
+                    &renderer.api,
+                    &mut materials[3],
+                    "t_Bloom",
+                    bright.color_attachment(0),
+                );
+
+                let triangle = &self.fullscreen_triangle;
+                draw(renderer, &materials[0], triangle, bright.into());
+                draw(renderer, &materials[1], triangle, blurred.into());
+                draw(renderer, &materials[2], triangle, bright.into());
+                draw(renderer, &materials[3], triangle, output);
+            } else {
+                set_texture(&renderer.api, &mut materials[0], "t_Input", input.clone());
+                if let Effect::ColorGrading { .. } = effect.effect {
+                    set_texture(&renderer.api, &mut materials[0], "t_Lut", self.lut.clone());
+                }
+                draw(renderer, &materials[0], &self.fullscreen_triangle, output);
+            }
+
+            if let RenderTarget::Framebuffer(framebuffer) = output {
+                input = framebuffer.color_attachment(0);
+            }
+        }
+    }
+}
+
+fn update_uniforms(effect: &mut PostEffect, width: u32, height: u32, lut_size: u32) {
+    let texel_size = Vec2::new(1.0 / width.max(1) as f32, 1.0 / height.max(1) as f32);
+    let materials = &mut effect.materials;
+    let uniforms: Vec<(usize, &str, UniformValue)> = match effect.effect {
+        Effect::ToneMapping { exposure } => vec![(0, "u_Exposure", UniformValue::Float(exposure))],
+        Effect::Bloom {
+            threshold,
+            intensity,
+            radius,
+        } => {
+            // the blur runs at half resolution
+            let texel = texel_size * 2.0 * radius;
+            vec![
+                (0, "u_Threshold", UniformValue::Float(threshold)),
+                (
+                    1,
+                    "u_Direction",
+                    UniformValue::Vec2(Vec2::new(texel.x(), 0.0)),
+                ),
+                (
+                    2,
+                    "u_Direction",
+                    UniformValue::Vec2(Vec2::new(0.0, texel.y())),
+                ),
+                (3, "u_Intensity", UniformValue::Float(intensity)),
+            ]
+        }
+        Effect::Vignette {
+            intensity,
+            smoothness,
+        } => vec![
+            (0, "u_Intensity", UniformValue::Float(intensity)),
+            (0, "u_Smoothness", UniformValue::Float(smoothness)),
+        ],
+        Effect::ColorGrading { strength } => vec![
+            (0, "u_Strength", UniformValue::Float(strength)),
+            (0, "u_LutSize", UniformValue::Float(lut_size as f32)),
+        ],
+        Effect::Fxaa => vec![(0, "u_TexelSize", UniformValue::Vec2(texel_size))],
+        Effect::Crt {
+            curvature,
+            scanline_intensity,
+            scanline_count,
+        } => vec![
+            (0, "u_Curvature", UniformValue::Float(curvature)),
+            (
+                0,
+                "u_ScanlineIntensity",
+                UniformValue::Float(scanline_intensity),
+            ),
+            (0, "u_ScanlineCount", UniformValue::Float(scanline_count)),
+        ],
+        Effect::Pixelate { pixel_size } => vec![
+            (
+                0,
+                "u_Resolution",
+                UniformValue::Vec2(Vec2::new(width as f32, height as f32)),
+            ),
+            (0, "u_PixelSize", UniformValue::Float(pixel_size)),
+        ],
+    };
+
+    for (material, name, value) in uniforms {
+        if materials[material].uniform(name) != Some(value) {
+            if let Err(e) = materials[material].set_uniform(name, value) {
+                log::error!("{:?}", e);
+            }
+        }
+    }
+}
+
+/// Rebinding is skipped when the texture didn't change
+fn set_texture(api: &RendererApi, material: &mut Material, name: &str, texture: Rc<Texture>) {
+    if material
+        .texture(name)
+        .map_or(false, |current| Rc::ptr_eq(current, &texture))
+    {
+        return;
+    }
+    if let Err(e) = material.set_texture(&api.device, name, texture) {
+        log::error!("{:?}", e);
+    }
+}
+
+fn draw(
+    renderer: &mut Renderer,
+    material: &Material,
+    triangle: &VertexArray,
+    target: RenderTarget,
+) {
+    let mut pass = renderer.begin_pass(target, PassLoad::Load);
+    pass.draw(material, triangle, Mat4::identity());
+    renderer.end_pass(pass);
+}
+
+/// Lut that leaves the colors as they are
+fn identity_lut(api: &mut RendererApi) -> Texture {
+    let size = LUT_SIZE;
+    let scale = |cell: u32| (cell * 255 / (size - 1)) as u8;
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size * size {
+            data.extend_from_slice(&[scale(x % size), scale(y), scale(x / size), 255]);
+        }
+    }
+    Texture::from_rgba(api, size * size, size, &data, Some("Identity lut"))
+}
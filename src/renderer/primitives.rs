@@ -1,27 +1,20 @@
-use super::buffer::{IndexBuffer, VertexBuffer, VertexBufferLayout};
-use wgpu::vertex_attr_array;
+use super::buffer::{BufferLayout, IndexBuffer, IndexType, VertexBuffer, VertexBufferLayout};
+use std::{
+    any::{Any, TypeId},
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, VertexBufferLayout)]
 pub struct VertexPos {
     pub position: [f32; 3],
 }
 unsafe impl bytemuck::Pod for VertexPos {}
 unsafe impl bytemuck::Zeroable for VertexPos {}
 
-impl VertexBufferLayout for VertexPos {
-    fn descriptor<'a>() -> wgpu::VertexBufferDescriptor<'a> {
-        use std::mem;
-        wgpu::VertexBufferDescriptor {
-            stride: mem::size_of::<Self>() as wgpu::BufferAddress,
-            step_mode: wgpu::InputStepMode::Vertex,
-            attributes: &vertex_attr_array![0 => Float3],
-        }
-    }
-}
-
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, VertexBufferLayout)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 4],
@@ -29,30 +22,215 @@ pub struct Vertex {
 unsafe impl bytemuck::Pod for Vertex {}
 unsafe impl bytemuck::Zeroable for Vertex {}
 
-impl VertexBufferLayout for Vertex {
-    fn descriptor<'a>() -> wgpu::VertexBufferDescriptor<'a> {
-        use std::mem;
-        wgpu::VertexBufferDescriptor {
-            stride: mem::size_of::<Self>() as wgpu::BufferAddress,
-            step_mode: wgpu::InputStepMode::Vertex,
-            attributes: &vertex_attr_array![0 => Float3, 1 => Float4],
-        }
+/// Type erased `VertexBuffer<T>` so a vertex array can hold buffers of different layouts
+trait AnyVertexBuffer {
+    fn buffer(&self) -> &wgpu::Buffer;
+    fn len(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T> AnyVertexBuffer for VertexBuffer<T>
+where
+    T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+{
+    fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    fn len(&self) -> usize {
+        VertexBuffer::len(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
-pub struct VertexArray<T> {
-    pub vertex_buffer: VertexBuffer<T>,
-    pub index_buffer: IndexBuffer,
+struct VertexArrayBuffer {
+    buffer: Box<dyn AnyVertexBuffer>,
+    layout_type: TypeId,
+    layout: BufferLayout,
+    step_mode: wgpu::InputStepMode,
+    attributes: Vec<wgpu::VertexAttributeDescriptor>,
 }
 
-impl<T> VertexArray<T>
-where
-    T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable,
-{
-    pub fn create(device: &wgpu::Device, vertices: &[T], indices: &[u16]) -> Self {
-        Self {
-            vertex_buffer: VertexBuffer::create(device, vertices),
-            index_buffer: IndexBuffer::create(device, indices),
+/// A set of vertex buffers with an optional index buffer
+///
+/// Each buffer takes the next shader locations, in the order the buffers are added.
+/// With a position buffer, a uv buffer and a per instance transform buffer:
+/// ```glsl
+///     layout(location = 0) in vec3 a_Position;
+///     layout(location = 1) in vec2 a_TexCoord;
+///     layout(location = 2) in mat4 a_Transform; // takes 2 to 5
+/// ```
+#[derive(Default)]
+pub struct VertexArray {
+    buffers: Vec<VertexArrayBuffer>,
+    /// Vertices are drawn in order when there's no index buffer
+    pub index_buffer: Option<IndexBuffer>,
+    next_location: u32,
+}
+
+impl VertexArray {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create<T, I>(device: &wgpu::Device, vertices: &[T], indices: &[I]) -> Self
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+        I: IndexType,
+    {
+        let mut vertex_array = Self::create_non_indexed(device, vertices);
+        vertex_array.index_buffer = Some(IndexBuffer::create(device, indices));
+        vertex_array
+    }
+
+    pub fn create_non_indexed<T>(device: &wgpu::Device, vertices: &[T]) -> Self
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        let mut vertex_array = Self::new();
+        vertex_array.add_vertex_buffer(VertexBuffer::create(device, vertices));
+        vertex_array
+    }
+
+    /// Every draw of this vertex array will draw one instance per element
+    pub fn with_instances<T>(mut self, device: &wgpu::Device, instances: &[T]) -> Self
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        self.add_instance_buffer(VertexBuffer::create(device, instances));
+        self
+    }
+
+    /// Returns the slot of the buffer
+    pub fn add_vertex_buffer<T>(&mut self, vertex_buffer: VertexBuffer<T>) -> usize
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        self.add_buffer(vertex_buffer, wgpu::InputStepMode::Vertex)
+    }
+
+    /// Returns the slot of the buffer
+    pub fn add_instance_buffer<T>(&mut self, instance_buffer: VertexBuffer<T>) -> usize
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        self.add_buffer(instance_buffer, wgpu::InputStepMode::Instance)
+    }
+
+    fn add_buffer<T>(&mut self, buffer: VertexBuffer<T>, step_mode: wgpu::InputStepMode) -> usize
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        let layout = T::layout();
+        debug_assert_eq!(
+            layout.stride(),
+            std::mem::size_of::<T>(),
+            "The layout of a vertex type needs to match its size"
+        );
+
+        let attributes = layout.attributes(self.next_location);
+        self.next_location += layout.location_count();
+        self.buffers.push(VertexArrayBuffer {
+            buffer: Box::new(buffer),
+            layout_type: TypeId::of::<T>(),
+            layout,
+            step_mode,
+            attributes,
+        });
+        self.buffers.len() - 1
+    }
+
+    pub fn vertex_buffer<T>(&self, slot: usize) -> Option<&VertexBuffer<T>>
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        self.buffers
+            .get(slot)
+            .and_then(|buffer| buffer.buffer.as_any().downcast_ref())
+    }
+
+    /// Used to write to a buffer after it was added
+    pub fn vertex_buffer_mut<T>(&mut self, slot: usize) -> Option<&mut VertexBuffer<T>>
+    where
+        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable + 'static,
+    {
+        self.buffers
+            .get_mut(slot)
+            .and_then(|buffer| buffer.buffer.as_any_mut().downcast_mut())
+    }
+
+    pub fn layout(&self, slot: usize) -> Option<&BufferLayout> {
+        self.buffers.get(slot).map(|buffer| &buffer.layout)
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.count(wgpu::InputStepMode::Vertex).unwrap_or(0)
+    }
+
+    pub fn instance_count(&self) -> u32 {
+        self.count(wgpu::InputStepMode::Instance).unwrap_or(1)
+    }
+
+    /// The smallest buffer of a step mode limits how much can be drawn
+    fn count(&self, step_mode: wgpu::InputStepMode) -> Option<u32> {
+        self.buffers
+            .iter()
+            .filter(|buffer| buffer.step_mode == step_mode)
+            .map(|buffer| buffer.buffer.len() as u32)
+            .min()
+    }
+
+    /// Pipelines need a format even when nothing is indexed
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_buffer
+            .as_ref()
+            .map_or(wgpu::IndexFormat::Uint16, |index_buffer| {
+                index_buffer.format
+            })
+    }
+
+    /// Identifies the layouts of the buffers, vertex arrays with the same id can share pipelines
+    pub fn layout_id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for buffer in self.buffers.iter() {
+            buffer.layout_type.hash(&mut hasher);
+            (buffer.step_mode == wgpu::InputStepMode::Instance).hash(&mut hasher);
+        }
+        (self.index_format() == wgpu::IndexFormat::Uint32).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn descriptors(&self) -> Vec<wgpu::VertexBufferDescriptor> {
+        self.buffers
+            .iter()
+            .map(|buffer| wgpu::VertexBufferDescriptor {
+                stride: buffer.layout.stride() as wgpu::BufferAddress,
+                step_mode: buffer.step_mode,
+                attributes: &buffer.attributes,
+            })
+            .collect()
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        for (slot, buffer) in self.buffers.iter().enumerate() {
+            render_pass.set_vertex_buffer(slot as u32, buffer.buffer.buffer(), 0, 0);
+        }
+
+        let instances = 0..self.instance_count();
+        match self.index_buffer.as_ref() {
+            Some(index_buffer) => {
+                render_pass.set_index_buffer(&index_buffer.buffer, 0, 0);
+                render_pass.draw_indexed(0..index_buffer.count, 0, instances);
+            }
+            None => render_pass.draw(0..self.vertex_count(), instances),
         }
     }
 }
@@ -0,0 +1,438 @@
+use super::{
+    framebuffer::{Framebuffer, FramebufferSpec, RenderTarget},
+    texture::Texture,
+    Renderer,
+};
+use crate::Frame;
+use anyhow::{anyhow, Result};
+use std::rc::Rc;
+
+/// Handle to a resource of a `RenderGraph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+impl ResourceId {
+    /// The swap chain frame, passes writing to it are never culled
+    pub const FRAME: ResourceId = ResourceId(0);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TargetSize {
+    /// Same size as the frame
+    Frame,
+    /// Relative to the frame, 0.5 is half the width and half the height
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+/// A transient render target, it only exists between the first and last pass using it
+#[derive(Clone, Debug)]
+pub struct TargetDesc {
+    pub size: TargetSize,
+    pub color_formats: Vec<wgpu::TextureFormat>,
+    pub depth: bool,
+}
+
+impl Default for TargetDesc {
+    fn default() -> Self {
+        Self {
+            size: TargetSize::Frame,
+            color_formats: vec![wgpu::TextureFormat::Rgba8UnormSrgb],
+            depth: true,
+        }
+    }
+}
+
+/// Resources available to a pass while it executes
+pub struct PassResources<'a> {
+    frame: &'a Frame,
+    targets: &'a [Option<Framebuffer>],
+}
+
+impl<'a> PassResources<'a> {
+    /// Only valid for resources the pass declared
+    pub fn target(&self, id: ResourceId) -> RenderTarget<'a> {
+        if id == ResourceId::FRAME {
+            RenderTarget::Frame(self.frame)
+        } else {
+            RenderTarget::Framebuffer(self.framebuffer(id))
+        }
+    }
+
+    pub fn framebuffer(&self, id: ResourceId) -> &'a Framebuffer {
+        self.targets[id.0]
+            .as_ref()
+            .expect("Resource isn't allocated during this pass")
+    }
+
+    /// First color attachment, usually bound to a material with `Material::set_texture`
+    pub fn texture(&self, id: ResourceId) -> Rc<Texture> {
+        self.framebuffer(id).color_attachment(0)
+    }
+}
+
+type ExecuteFn<'a> = Box<dyn FnOnce(&mut Renderer, &PassResources) + 'a>;
+
+struct Pass<'a> {
+    name: String,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    execute: ExecuteFn<'a>,
+}
+
+/// Passes of a frame with the resources they read and write
+///
+/// Passes run in the order they were added, a pass reads what the passes added before it wrote, so
+/// a resource can be written again after it's read. Passes that don't contribute to the frame are
+/// culled.
+/// ```rust,ignore
+///     let mut graph = RenderGraph::new();
+///     let scene = graph.create_target("scene", TargetDesc::default());
+///     graph.add_pass("scene", &[], &[scene], |renderer, resources| { ... });
+///     graph.add_pass("composite", &[scene], &[ResourceId::FRAME], |renderer, resources| { ... });
+///     app.renderer.execute_graph(graph, frame)?;
+/// ```
+pub struct RenderGraph<'a> {
+    resources: Vec<(String, Option<TargetDesc>)>,
+    passes: Vec<Pass<'a>>,
+}
+
+impl<'a> Default for RenderGraph<'a> {
+    fn default() -> Self {
+        Self {
+            resources: vec![(String::from("frame"), None)],
+            passes: vec![],
+        }
+    }
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create_target(&mut self, name: &str, desc: TargetDesc) -> ResourceId {
+        self.resources.push((String::from(name), Some(desc)));
+        ResourceId(self.resources.len() - 1)
+    }
+
+    pub fn add_pass<F>(
+        &mut self,
+        name: &str,
+        reads: &[ResourceId],
+        writes: &[ResourceId],
+        execute: F,
+    ) where
+        F: FnOnce(&mut Renderer, &PassResources) + 'a,
+    {
+        self.passes.push(Pass {
+            name: String::from(name),
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            execute: Box::new(execute),
+        });
+    }
+
+    /// Order the passes are executed in, culled passes are left out
+    fn compile(&self) -> Result<Vec<usize>> {
+        let pass_count = self.passes.len();
+        let writes =
+            |pass: usize, resource: &ResourceId| self.passes[pass].writes.contains(resource);
+
+        // a read depends on the latest writer before it. Passes run in the order they were added
+        // so a write always comes after the passes reading or writing what it overwrites, those
+        // don't make the earlier passes needed
+        let mut dependencies = vec![vec![]; pass_count];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for resource in pass.reads.iter() {
+                match (0..i).rev().find(|&j| writes(j, resource)) {
+                    Some(j) => dependencies[i].push(j),
+                    None => {
+                        // it could only be read after a pass that needs it
+                        if let Some(j) = (i + 1..pass_count).find(|&j| writes(j, resource)) {
+                            return Err(anyhow!(
+                                "Render graph has a cycle, {:?} reads {:?} before {:?} writes it",
+                                pass.name,
+                                self.resources[resource.0].0,
+                                self.passes[j].name
+                            ));
+                        }
+                    }
+                }
+            }
+            dependencies[i].sort();
+            dependencies[i].dedup();
+        }
+
+        // everything the frame depends on is needed
+        let mut needed = vec![false; pass_count];
+        let mut stack = (0..pass_count)
+            .filter(|&i| writes(i, &ResourceId::FRAME))
+            .collect::<Vec<_>>();
+        while let Some(i) = stack.pop() {
+            if !needed[i] {
+                needed[i] = true;
+                stack.extend(dependencies[i].iter().copied());
+            }
+        }
+
+        // the passes read are always added before, so the order they were added in works
+        Ok((0..pass_count).filter(|&i| needed[i]).collect())
+    }
+
+    fn spec(&self, desc: &TargetDesc, frame_size: (u32, u32)) -> FramebufferSpec {
+        let (width, height) = match desc.size {
+            TargetSize::Frame => frame_size,
+            TargetSize::Scaled(scale) => (
+                ((frame_size.0 as f32 * scale) as u32).max(1),
+                ((frame_size.1 as f32 * scale) as u32).max(1),
+            ),
+            TargetSize::Fixed(width, height) => (width, height),
+        };
+        FramebufferSpec {
+            width,
+            height,
+            color_formats: desc.color_formats.clone(),
+            depth: desc.depth,
+            sample_count: 1,
+        }
+    }
+}
+
+/// Framebuffers of previous frames, reused by transient resources with the same spec
+#[derive(Default)]
+pub(crate) struct TargetPool {
+    free: Vec<Framebuffer>,
+}
+
+impl TargetPool {
+    fn acquire(&mut self, device: &wgpu::Device, spec: FramebufferSpec) -> Framebuffer {
+        match self
+            .free
+            .iter()
+            .position(|framebuffer| *framebuffer.spec() == spec)
+        {
+            Some(index) => self.free.swap_remove(index),
+            None => Framebuffer::new(device, spec),
+        }
+    }
+
+    fn release(&mut self, framebuffer: Framebuffer) {
+        self.free.push(framebuffer);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PassInfo {
+    pub name: String,
+    pub culled: bool,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResourceInfo {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Index in the execution order of the first and last pass using it
+    pub lifetime: Option<(usize, usize)>,
+}
+
+/// What the last executed graph looked like, for the debug window
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraphInfo {
+    /// In execution order, followed by the culled passes
+    pub passes: Vec<PassInfo>,
+    pub resources: Vec<ResourceInfo>,
+    /// Framebuffers kept for the next frames
+    pub pooled_targets: usize,
+}
+
+impl Renderer {
+    /// Executes the passes of the graph on the frame encoder
+    pub fn execute_graph(&mut self, graph: RenderGraph, frame: &Frame) -> Result<()> {
+        let order = graph.compile()?;
+        let frame_size = (self.api.sc_desc.width, self.api.sc_desc.height);
+
+        let mut lifetimes = vec![None; graph.resources.len()];
+        for (position, &pass) in order.iter().enumerate() {
+            let pass = &graph.passes[pass];
+            for resource in pass.reads.iter().chain(pass.writes.iter()) {
+                let lifetime = &mut lifetimes[resource.0];
+                *lifetime = match *lifetime {
+                    None => Some((position, position)),
+                    Some((first, _)) => Some((first, position)),
+                };
+            }
+        }
+
+        let specs = graph
+            .resources
+            .iter()
+            .map(|(_, desc)| desc.as_ref().map(|desc| graph.spec(desc, frame_size)))
+            .collect::<Vec<_>>();
+        self.graph_info = RenderGraphInfo {
+            passes: order
+                .iter()
+                .map(|&i| (i, false))
+                .chain(
+                    (0..graph.passes.len())
+                        .filter(|i| !order.contains(i))
+                        .map(|i| (i, true)),
+                )
+                .map(|(i, culled)| {
+                    let pass = &graph.passes[i];
+                    let names = |ids: &[ResourceId]| -> Vec<String> {
+                        ids.iter()
+                            .map(|id| graph.resources[id.0].0.clone())
+                            .collect()
+                    };
+                    PassInfo {
+                        name: pass.name.clone(),
+                        culled,
+                        reads: names(&pass.reads),
+                        writes: names(&pass.writes),
+                    }
+                })
+                .collect(),
+            resources: graph
+                .resources
+                .iter()
+                .zip(specs.iter())
+                .zip(lifetimes.iter())
+                .map(|(((name, _), spec), lifetime)| {
+                    let (width, height) = spec
+                        .as_ref()
+                        .map_or(frame_size, |spec| (spec.width, spec.height));
+                    ResourceInfo {
+                        name: name.clone(),
+                        width,
+                        height,
+                        lifetime: *lifetime,
+                    }
+                })
+                .collect(),
+            pooled_targets: 0,
+        };
+
+        // the pool is taken out so the passes can borrow the renderer
+        let mut pool = std::mem::take(&mut self.target_pool);
+        let mut targets = (0..graph.resources.len())
+            .map(|_| None)
+            .collect::<Vec<Option<Framebuffer>>>();
+        let mut passes = graph.passes.into_iter().map(Some).collect::<Vec<_>>();
+
+        for (position, &i) in order.iter().enumerate() {
+            for (resource, lifetime) in lifetimes.iter().enumerate() {
+                if let (Some((first, _)), Some(spec)) = (lifetime, &specs[resource]) {
+                    if *first == position {
+                        targets[resource] = Some(pool.acquire(&self.api.device, spec.clone()));
+                    }
+                }
+            }
+
+            let pass = passes[i].take().expect("Pass executed twice");
+            (pass.execute)(
+                self,
+                &PassResources {
+                    frame,
+                    targets: &targets,
+                },
+            );
+
+            // released targets can be reused by the next passes
+            for (resource, lifetime) in lifetimes.iter().enumerate() {
+                if let Some((_, last)) = lifetime {
+                    if *last == position {
+                        if let Some(framebuffer) = targets[resource].take() {
+                            pool.release(framebuffer);
+                        }
+                    }
+                }
+            }
+        }
+
+        // targets of a previous size or of resources that are gone aren't kept around
+        pool.free.retain(|framebuffer| {
+            specs
+                .iter()
+                .any(|spec| spec.as_ref() == Some(framebuffer.spec()))
+        });
+        self.graph_info.pooled_targets = pool.free.len();
+        self.target_pool = pool;
+        Ok(())
+    }
+
+    /// Passes and resources of the last executed render graph
+    pub fn graph_info(&self) -> &RenderGraphInfo {
+        &self.graph_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph_with_targets(count: usize) -> (RenderGraph<'static>, Vec<ResourceId>) {
+        let mut graph = RenderGraph::new();
+        let targets = (0..count)
+            .map(|i| graph.create_target(&format!("target {}", i), TargetDesc::default()))
+            .collect();
+        (graph, targets)
+    }
+
+    #[test]
+    fn ping_pong() {
+        let (mut graph, targets) = graph_with_targets(2);
+        let (x, y) = (targets[0], targets[1]);
+        graph.add_pass("a", &[], &[x], |_, _| {});
+        graph.add_pass("b", &[x], &[y], |_, _| {});
+        graph.add_pass("c", &[y], &[x], |_, _| {});
+        graph.add_pass("composite", &[x], &[ResourceId::FRAME], |_, _| {});
+        assert_eq!(graph.compile().unwrap(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn reads_the_latest_write() {
+        let (mut graph, targets) = graph_with_targets(1);
+        let x = targets[0];
+        graph.add_pass("first write", &[], &[x], |_, _| {});
+        graph.add_pass("first read", &[x], &[ResourceId::FRAME], |_, _| {});
+        graph.add_pass("second write", &[], &[x], |_, _| {});
+        graph.add_pass("second read", &[x], &[ResourceId::FRAME], |_, _| {});
+        assert_eq!(graph.compile().unwrap(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn culls_passes_not_reaching_the_frame() {
+        let (mut graph, targets) = graph_with_targets(3);
+        let (scene, unused, unread) = (targets[0], targets[1], targets[2]);
+        graph.add_pass("unread", &[], &[unread], |_, _| {});
+        graph.add_pass("scene", &[], &[scene], |_, _| {});
+        graph.add_pass("unused", &[scene], &[unused], |_, _| {});
+        graph.add_pass("composite", &[scene], &[ResourceId::FRAME], |_, _| {});
+        assert_eq!(graph.compile().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn overwritten_reads_are_culled() {
+        let (mut graph, targets) = graph_with_targets(2);
+        let (x, unused) = (targets[0], targets[1]);
+        graph.add_pass("a", &[], &[x], |_, _| {});
+        graph.add_pass("b", &[x], &[unused], |_, _| {});
+        graph.add_pass("c", &[], &[x], |_, _| {});
+        graph.add_pass("composite", &[x], &[ResourceId::FRAME], |_, _| {});
+        assert_eq!(graph.compile().unwrap(), vec![2, 3]);
+    }
+
+    #[test]
+    fn cycle() {
+        let (mut graph, targets) = graph_with_targets(2);
+        let (x, y) = (targets[0], targets[1]);
+        graph.add_pass("a", &[y], &[x], |_, _| {});
+        graph.add_pass("b", &[x], &[y], |_, _| {});
+        graph.add_pass("composite", &[y], &[ResourceId::FRAME], |_, _| {});
+        assert!(graph.compile().is_err());
+    }
+}
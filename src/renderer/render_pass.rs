@@ -0,0 +1,172 @@
+use super::{framebuffer::RenderTarget, material::Material, primitives::VertexArray};
+use glam::Mat4;
+use std::cmp::Ordering;
+
+/// How a pass starts, `Load` keeps what was already drawn to the target
+#[derive(Clone, Copy, Debug)]
+pub enum PassLoad {
+    Load,
+    /// Clears the color attachments to the color and the depth to the far plane
+    Clear(wgpu::Color),
+}
+
+/// Order the draws of a pass are recorded in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortMode {
+    /// Recorded in submission order
+    Submission,
+    /// Back to front, a bigger z is closer to the camera.
+    /// Draws with the same z keep their submission order so 2D layering still works
+    Depth,
+    /// Grouped by pipeline and material to avoid state changes, then back to front.
+    /// Only use it when the depth test takes care of the order
+    Material,
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        SortMode::Depth
+    }
+}
+
+pub struct DrawCommand<'a> {
+    pub material: &'a Material,
+    pub vertex_array: &'a VertexArray,
+    pub transform: Mat4,
+}
+
+impl<'a> DrawCommand<'a> {
+    fn z(&self) -> f32 {
+        self.transform.w_axis().z()
+    }
+}
+
+/// Draws recorded in a single wgpu render pass when the pass ends
+///
+/// Nothing is recorded on the encoder until `Renderer::end_pass`, this is what lets
+/// every transform be uploaded before the pass starts.
+/// ```rust,ignore
+///     let mut pass = app.renderer.begin_pass(frame, PassLoad::Clear(color));
+///     pass.draw(&material, &vertex_array, transform);
+///     app.renderer.end_pass(pass);
+/// ```
+pub struct RenderPass<'a> {
+    pub target: RenderTarget<'a>,
+    pub load: PassLoad,
+    pub sort_mode: SortMode,
+    pub(crate) commands: Vec<DrawCommand<'a>>,
+}
+
+impl<'a> RenderPass<'a> {
+    pub fn new(target: RenderTarget<'a>, load: PassLoad) -> Self {
+        Self {
+            target,
+            load,
+            sort_mode: SortMode::default(),
+            commands: vec![],
+        }
+    }
+
+    /// Vertex arrays with an instance buffer draw one instance per element
+    pub fn draw(&mut self, material: &'a Material, vertex_array: &'a VertexArray, transform: Mat4) {
+        self.commands.push(DrawCommand {
+            material,
+            vertex_array,
+            transform,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// The sort is stable, draws that compare equal keep their submission order
+    pub(crate) fn sort(&mut self) {
+        let by_depth =
+            |a: &DrawCommand, b: &DrawCommand| a.z().partial_cmp(&b.z()).unwrap_or(Ordering::Equal);
+        match self.sort_mode {
+            SortMode::Submission => {}
+            SortMode::Depth => self.commands.sort_by(by_depth),
+            SortMode::Material => self.commands.sort_by(|a, b| {
+                a.material
+                    .id()
+                    .cmp(&b.material.id())
+                    .then(a.vertex_array.layout_id().cmp(&b.vertex_array.layout_id()))
+                    .then_with(|| by_depth(a, b))
+            }),
+        }
+    }
+}
+
+/// Draws submitted during a scene, grouped in one pass per target
+///
+/// Consecutive draws to the same target share a pass, a new pass starts every time the target
+/// changes so a target is never drawn before what was submitted to another target ahead of it.
+pub struct RenderQueue<'a> {
+    pub sort_mode: SortMode,
+    pub(crate) passes: Vec<RenderPass<'a>>,
+}
+
+impl<'a> Default for RenderQueue<'a> {
+    fn default() -> Self {
+        Self {
+            sort_mode: SortMode::default(),
+            passes: vec![],
+        }
+    }
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the target at the start of its pass instead of in a pass of its own
+    pub fn clear(&mut self, target: impl Into<RenderTarget<'a>>, color: wgpu::Color) {
+        let target = target.into();
+        match self.passes.last_mut() {
+            Some(pass) if pass.target.same_target(&target) && pass.is_empty() => {
+                pass.load = PassLoad::Clear(color)
+            }
+            _ => self.push_pass(target, PassLoad::Clear(color)),
+        }
+    }
+
+    pub fn submit(
+        &mut self,
+        material: &'a Material,
+        vertex_array: &'a VertexArray,
+        transform: Mat4,
+        target: impl Into<RenderTarget<'a>>,
+    ) {
+        let target = target.into();
+        let same_target = self
+            .passes
+            .last()
+            .map_or(false, |pass| pass.target.same_target(&target));
+        if !same_target {
+            self.push_pass(target, PassLoad::Load);
+        }
+        if let Some(pass) = self.passes.last_mut() {
+            pass.draw(material, vertex_array, transform);
+        }
+    }
+
+    fn push_pass(&mut self, target: RenderTarget<'a>, load: PassLoad) {
+        let mut pass = RenderPass::new(target, load);
+        pass.sort_mode = self.sort_mode;
+        self.passes.push(pass);
+    }
+}
+
+/// Counters of the last complete frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    pub passes: u32,
+    pub draw_calls: u32,
+    pub pipeline_switches: u32,
+}
@@ -0,0 +1,393 @@
+use super::{
+    buffer::{IndexBuffer, VertexBuffer, VertexBufferLayout},
+    camera::Camera,
+    font::{Font, TextOptions},
+    framebuffer::RenderTarget,
+    material::Material,
+    nine_slice::NineSlice,
+    pipeline::RenderState,
+    primitives::VertexArray,
+    render_pass::SortMode,
+    renderer_api::RendererApi,
+    shader::{CompileOptions, Shader},
+    shader_cache::ShaderCache,
+    sub_texture::SubTexture2D,
+    texture::Texture,
+    Renderer,
+};
+use anyhow::Result;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use std::{cmp::Ordering, rc::Rc};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, VertexBufferLayout)]
+pub struct QuadVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+    pub tex_coord: [f32; 2],
+    /// Only used by text
+    pub outline_color: [f32; 4],
+    /// Only used by text, in distance field units
+    pub outline_width: f32,
+}
+unsafe impl bytemuck::Pod for QuadVertex {}
+unsafe impl bytemuck::Zeroable for QuadVertex {}
+
+/// Corners of a unit quad centered on the origin, in the order of `SubTexture2D::tex_coords`
+const QUAD_POSITIONS: [[f32; 2]; 4] = [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+const FULL_TEX_COORDS: [[f32; 2]; 4] = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+/// Materials of textures that haven't been drawn for this many frames are dropped
+const MATERIAL_LIFETIME: u64 = 60;
+
+struct Quad {
+    vertices: [QuadVertex; 4],
+    texture: Rc<Texture>,
+    /// Text is drawn from a distance field by another shader
+    text: bool,
+    z: f32,
+}
+
+/// Counters of the last `end_scene`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Renderer2DStats {
+    pub quads: u32,
+    pub batches: u32,
+}
+
+/// Batches quads sharing a texture in a single draw call
+///
+/// Quads are drawn from the smallest z to the biggest, quads with the same z keep the order
+/// they were drawn in. A new batch starts every time the texture changes, sprites of the
+/// same `SpriteSheet` or texture atlas don't break the batch. Text is drawn with another shader
+/// and starts a batch too.
+/// ```rust,ignore
+///     let renderer_2d = &mut app.renderer_2d;
+///     renderer_2d.draw_quad(Vec3::new(0.0, 0.0, 0.0), Vec2::new(1.0, 1.0), Vec4::new(0.8, 0.2, 0.3, 1.0));
+///     renderer_2d.draw_sprite(Vec3::new(1.0, 0.0, 0.1), Vec2::new(1.0, 1.0), &sprite, Vec4::one());
+///     renderer_2d.end_scene(&mut app.renderer, &app.camera_controller.camera, frame.into());
+/// ```
+pub struct Renderer2D {
+    /// Applied to every quad, alpha blended by default
+    pub render_state: RenderState,
+    /// Bigger batches are split
+    pub max_quads_per_batch: usize,
+    shader: Rc<Shader>,
+    text_shader: Rc<Shader>,
+    white_texture: Rc<Texture>,
+    quads: Vec<Quad>,
+    /// Reused every scene, one per batch
+    vertex_arrays: Vec<VertexArray>,
+    /// Keyed by texture and by whether it's drawing text, with the frame it was last drawn in
+    materials: Vec<(Rc<Texture>, bool, Material, u64)>,
+    stats: Renderer2DStats,
+}
+
+impl Renderer2D {
+    pub fn new(api: &mut RendererApi, shader_cache: &ShaderCache) -> Result<Self> {
+        let shader = shader_cache.load(
+            include_str!("../assets/shaders/renderer_2d/quad.vert.glsl"),
+            include_str!("../assets/shaders/renderer_2d/quad.frag.glsl"),
+            &CompileOptions::default(),
+        )?;
+        let text_shader = shader_cache.load(
+            include_str!("../assets/shaders/renderer_2d/quad.vert.glsl"),
+            include_str!("../assets/shaders/renderer_2d/text.frag.glsl"),
+            &CompileOptions::default(),
+        )?;
+        Ok(Self {
+            render_state: RenderState::transparent(),
+            max_quads_per_batch: 10_000,
+            shader: Rc::new(shader),
+            text_shader: Rc::new(text_shader),
+            white_texture: Rc::new(Texture::white(api)),
+            quads: vec![],
+            vertex_arrays: vec![],
+            materials: vec![],
+            stats: Renderer2DStats::default(),
+        })
+    }
+
+    pub fn draw_quad(&mut self, position: Vec3, size: Vec2, color: Vec4) {
+        self.draw_rotated_quad(position, size, 0.0, color);
+    }
+
+    /// `rotation` is in radians, counter clockwise
+    pub fn draw_rotated_quad(&mut self, position: Vec3, size: Vec2, rotation: f32, color: Vec4) {
+        let texture = self.white_texture.clone();
+        self.draw_quad_with_transform(
+            quad_transform(position, size, rotation),
+            FULL_TEX_COORDS,
+            &texture,
+            color,
+        );
+    }
+
+    /// The texture is multiplied by `tint`, `Vec4::one()` draws it as is
+    pub fn draw_texture(&mut self, position: Vec3, size: Vec2, texture: &Rc<Texture>, tint: Vec4) {
+        self.draw_quad_with_transform(
+            quad_transform(position, size, 0.0),
+            FULL_TEX_COORDS,
+            texture,
+            tint,
+        );
+    }
+
+    /// `size` is the size of the untrimmed sprite, trimmed sprites of a sheet stay in place
+    pub fn draw_sprite(&mut self, position: Vec3, size: Vec2, sprite: &SubTexture2D, tint: Vec4) {
+        self.draw_rotated_sprite(position, size, 0.0, sprite, tint);
+    }
+
+    pub fn draw_rotated_sprite(
+        &mut self,
+        position: Vec3,
+        size: Vec2,
+        rotation: f32,
+        sprite: &SubTexture2D,
+        tint: Vec4,
+    ) {
+        // a trimmed sprite only covers part of the quad
+        let (center, size) = sprite.trimmed_quad(size);
+        let position = position + Quat::from_rotation_z(rotation).mul_vec3(center.extend(0.0));
+        let tex_coords = sprite.tex_coords();
+        self.draw_quad_with_transform(
+            quad_transform(position, size, rotation),
+            [
+                [tex_coords[0].x(), tex_coords[0].y()],
+                [tex_coords[1].x(), tex_coords[1].y()],
+                [tex_coords[2].x(), tex_coords[2].y()],
+                [tex_coords[3].x(), tex_coords[3].y()],
+            ],
+            &sprite.texture,
+            tint,
+        );
+    }
+
+    /// Panel centered on `position`, the borders keep their size and the rest is stretched or
+    /// tiled depending on the modes of the nine slice
+    pub fn draw_nine_slice(
+        &mut self,
+        position: Vec3,
+        size: Vec2,
+        nine_slice: &NineSlice,
+        tint: Vec4,
+    ) {
+        for (transform, tex_coords) in nine_slice.quads(position, size) {
+            self.draw_quad_with_transform(transform, tex_coords, &nine_slice.sprite.texture, tint);
+        }
+    }
+
+    /// `position` is the start of the baseline of the first line, lines go down
+    pub fn draw_text(&mut self, font: &Font, text: &str, position: Vec3, options: &TextOptions) {
+        let outline_width = font.outline_width(options.outline_width);
+        // without an outline the edges blend with the fill color
+        let outline_color = if outline_width > 0.0 {
+            options.outline_color
+        } else {
+            options.color
+        };
+        for (offset, glyph) in font.layout(text, options) {
+            let sprite = match &glyph.sprite {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+            let size = glyph.size * options.size;
+            let center = position + (offset + glyph.offset * options.size + size / 2.0).extend(0.0);
+            let tex_coords = sprite.tex_coords();
+            self.push_quad(
+                quad_transform(center, size, 0.0),
+                [
+                    [tex_coords[0].x(), tex_coords[0].y()],
+                    [tex_coords[1].x(), tex_coords[1].y()],
+                    [tex_coords[2].x(), tex_coords[2].y()],
+                    [tex_coords[3].x(), tex_coords[3].y()],
+                ],
+                &sprite.texture,
+                options.color,
+                Some((outline_color, outline_width)),
+            );
+        }
+    }
+
+    /// The transform is applied to a unit quad centered on the origin.
+    /// `tex_coords` are for the bottom left, bottom right, top right and top left corners
+    pub fn draw_quad_with_transform(
+        &mut self,
+        transform: Mat4,
+        tex_coords: [[f32; 2]; 4],
+        texture: &Rc<Texture>,
+        color: Vec4,
+    ) {
+        self.push_quad(transform, tex_coords, texture, color, None);
+    }
+
+    /// `outline` is the color and width of the outline of text, `None` for everything else
+    fn push_quad(
+        &mut self,
+        transform: Mat4,
+        tex_coords: [[f32; 2]; 4],
+        texture: &Rc<Texture>,
+        color: Vec4,
+        outline: Option<(Vec4, f32)>,
+    ) {
+        let (outline_color, outline_width) = outline.unwrap_or((Vec4::zero(), 0.0));
+        let mut vertices = [QuadVertex {
+            position: [0.0; 3],
+            color: [color.x(), color.y(), color.z(), color.w()],
+            tex_coord: [0.0; 2],
+            outline_color: [
+                outline_color.x(),
+                outline_color.y(),
+                outline_color.z(),
+                outline_color.w(),
+            ],
+            outline_width,
+        }; 4];
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            let [x, y] = QUAD_POSITIONS[i];
+            let position = transform * Vec4::new(x, y, 0.0, 1.0);
+            vertex.position = [position.x(), position.y(), position.z()];
+            vertex.tex_coord = tex_coords[i];
+        }
+        self.quads.push(Quad {
+            vertices,
+            texture: texture.clone(),
+            text: outline.is_some(),
+            z: transform.w_axis().z(),
+        });
+    }
+
+    /// Draws every quad since the last `end_scene` to the target, seen by the camera
+    pub fn end_scene(
+        &mut self,
+        renderer: &mut Renderer,
+        camera: &dyn Camera,
+        target: RenderTarget,
+    ) {
+        self.quads
+            .sort_by(|a, b| a.z.partial_cmp(&b.z).unwrap_or(Ordering::Equal));
+
+        // consecutive quads with the same texture share a batch
+        let mut batches: Vec<(usize, std::ops::Range<usize>)> = vec![];
+        for i in 0..self.quads.len() {
+            let texture = self.quads[i].texture.clone();
+            let text = self.quads[i].text;
+            match batches.last_mut() {
+                Some((material, range))
+                    if Rc::ptr_eq(&self.materials[*material].0, &texture)
+                        && self.materials[*material].1 == text
+                        && range.len() < self.max_quads_per_batch =>
+                {
+                    range.end = i + 1
+                }
+                _ => {
+                    let material = self.material(&renderer.api, &texture, text);
+                    batches.push((material, i..i + 1));
+                }
+            }
+        }
+
+        for (batch, (_, range)) in batches.iter().enumerate() {
+            if batch == self.vertex_arrays.len() {
+                let mut vertex_array = VertexArray::new();
+                vertex_array.add_vertex_buffer(VertexBuffer::<QuadVertex>::with_capacity(
+                    &renderer.api.device,
+                    range.len() * 4,
+                ));
+                vertex_array.index_buffer = Some(IndexBuffer::with_capacity::<u32>(
+                    &renderer.api.device,
+                    range.len() * 6,
+                ));
+                self.vertex_arrays.push(vertex_array);
+            }
+
+            let vertices = self.quads[range.clone()]
+                .iter()
+                .flat_map(|quad| quad.vertices.iter().copied())
+                .collect::<Vec<_>>();
+            let indices = (0..range.len() as u32)
+                .flat_map(|quad| {
+                    let i = quad * 4;
+                    vec![i, i + 1, i + 2, i + 2, i + 3, i]
+                })
+                .collect::<Vec<_>>();
+
+            let vertex_array = &mut self.vertex_arrays[batch];
+            if let Some(vertex_buffer) = vertex_array.vertex_buffer_mut::<QuadVertex>(0) {
+                vertex_buffer.set_data(&mut renderer.api, &vertices);
+            }
+            if let Some(index_buffer) = vertex_array.index_buffer.as_mut() {
+                index_buffer.clear();
+                index_buffer.write(&mut renderer.api, 0, &indices);
+            }
+        }
+
+        for (_, _, material, _) in self.materials.iter_mut() {
+            material.render_state = self.render_state;
+        }
+
+        let mut queue = renderer.begin_scene(camera);
+        // the quads are already in order
+        queue.sort_mode = SortMode::Submission;
+        for (batch, (material, _)) in batches.iter().enumerate() {
+            queue.submit(
+                &self.materials[*material].2,
+                &self.vertex_arrays[batch],
+                Mat4::identity(),
+                target,
+            );
+        }
+        renderer.end_scene(queue);
+
+        self.stats = Renderer2DStats {
+            quads: self.quads.len() as u32,
+            batches: batches.len() as u32,
+        };
+        self.quads.clear();
+        // the textures of old materials may only be kept alive by them
+        let frame_index = renderer.api.frame_index;
+        self.materials.retain(|(_, _, _, last_used)| {
+            frame_index.saturating_sub(*last_used) < MATERIAL_LIFETIME
+        });
+    }
+
+    pub fn stats(&self) -> Renderer2DStats {
+        self.stats
+    }
+
+    /// Index of the material drawing the texture, created the first time the texture is drawn
+    fn material(&mut self, api: &RendererApi, texture: &Rc<Texture>, text: bool) -> usize {
+        match self
+            .materials
+            .iter()
+            .position(|(material_texture, material_text, _, _)| {
+                Rc::ptr_eq(material_texture, texture) && *material_text == text
+            }) {
+            Some(index) => {
+                self.materials[index].3 = api.frame_index;
+                index
+            }
+            None => {
+                let shader = if text {
+                    self.text_shader.clone()
+                } else {
+                    self.shader.clone()
+                };
+                let mut material =
+                    Material::new(api, shader, &[], &[("t_Texture", texture.clone())]);
+                material.render_state = self.render_state;
+                self.materials
+                    .push((texture.clone(), text, material, api.frame_index));
+                self.materials.len() - 1
+            }
+        }
+    }
+}
+
+/// Unit quad scaled to `size`, rotated around its center and moved to `position`
+pub fn quad_transform(position: Vec3, size: Vec2, rotation: f32) -> Mat4 {
+    Mat4::from_scale_rotation_translation(
+        Vec3::new(size.x(), size.y(), 1.0),
+        Quat::from_rotation_z(rotation),
+        position,
+    )
+}
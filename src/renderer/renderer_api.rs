@@ -1,8 +1,85 @@
-use crate::Frame;
+use super::{
+    framebuffer::{RenderTarget, TargetFormat},
+    material,
+    staging_belt::StagingBelt,
+    texture::Texture,
+};
 use anyhow::{anyhow, Context, Result};
-use std::time::{Duration, Instant};
+use glam::Vec2;
+use mint::Point2;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use winit::window::Window;
 
+/// Sample counts supported for MSAA
+pub const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// The color attachment of the passes drawing to the frame
+///
+/// When multisampled, passes draw to a multisampled texture which is resolved in the frame
+pub struct ColorTarget {
+    pub sample_count: u32,
+    multisampled_view: Option<wgpu::TextureView>,
+}
+
+impl ColorTarget {
+    fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor, sample_count: u32) -> Self {
+        let multisampled_view = if sample_count > 1 {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Multisampled framebuffer"),
+                size: wgpu::Extent3d {
+                    width: sc_desc.width,
+                    height: sc_desc.height,
+                    depth: 1,
+                },
+                array_layer_count: 1,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: sc_desc.format,
+                usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            });
+            Some(texture.create_default_view())
+        } else {
+            None
+        };
+
+        Self {
+            sample_count,
+            multisampled_view,
+        }
+    }
+
+    /// Every pass resolves in the frame so the frame is always up to date for layers drawing to it directly
+    pub fn attachment<'a>(
+        &'a self,
+        frame_view: &'a wgpu::TextureView,
+        load_op: wgpu::LoadOp,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPassColorAttachmentDescriptor<'a> {
+        match self.multisampled_view.as_ref() {
+            Some(multisampled_view) => wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: multisampled_view,
+                resolve_target: Some(frame_view),
+                load_op,
+                store_op: wgpu::StoreOp::Store,
+                clear_color,
+            },
+            None => wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: frame_view,
+                resolve_target: None,
+                load_op,
+                store_op: wgpu::StoreOp::Store,
+                clear_color,
+            },
+        }
+    }
+}
+
 pub struct RendererApi {
     pub size: winit::dpi::PhysicalSize<u32>,
     pub last_frame: Instant,
@@ -16,7 +93,15 @@ pub struct RendererApi {
     scale_factor: f64,
     swap_chain: wgpu::SwapChain,
     pub encoder: wgpu::CommandEncoder,
+    /// Same size as the swap chain, cleared with the frame
+    pub depth_texture: Texture,
+    pub color_target: ColorTarget,
     pub v_sync: bool,
+    /// Incremented every time a frame is submitted
+    pub frame_index: u64,
+    staging_belt: StagingBelt,
+    /// Keyed by whether there is a uniform block and by the number of textures
+    material_layouts: RefCell<HashMap<(bool, usize), Rc<wgpu::BindGroupLayout>>>,
 }
 
 impl RendererApi {
@@ -24,7 +109,12 @@ impl RendererApi {
         window: &Window,
         clear_color: wgpu::Color,
         v_sync: bool,
+        sample_count: u32,
     ) -> anyhow::Result<Self> {
+        if !SAMPLE_COUNTS.contains(&sample_count) {
+            return Err(anyhow!("Unsupported sample count {}", sample_count));
+        }
+
         let size = window.inner_size();
         let surface = wgpu::Surface::create(window);
         let adapter = wgpu::Adapter::request(
@@ -60,6 +150,9 @@ impl RendererApi {
             },
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let depth_texture =
+            Texture::create_depth_texture(&device, size.width, size.height, sample_count);
+        let color_target = ColorTarget::new(&device, &sc_desc, sample_count);
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             bind_group_layouts: &[],
@@ -82,7 +175,12 @@ impl RendererApi {
             queue,
             pipeline_layout,
             encoder,
+            depth_texture,
+            color_target,
             v_sync,
+            frame_index: 0,
+            staging_belt: StagingBelt::new(1024 * 1024),
+            material_layouts: RefCell::new(HashMap::new()),
         })
     }
 
@@ -99,6 +197,12 @@ impl RendererApi {
     }
 
     pub fn end_render(&mut self) {
+        self.flush();
+        self.frame_index += 1;
+    }
+
+    /// Submits everything recorded so far without ending the frame
+    pub fn flush(&mut self) {
         let next_encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -107,7 +211,9 @@ impl RendererApi {
 
         let encoder = std::mem::replace(&mut self.encoder, next_encoder);
 
+        self.staging_belt.finish();
         self.queue.submit(&[encoder.finish()]);
+        self.staging_belt.recall();
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, scale_factor: Option<f64>) {
@@ -118,6 +224,76 @@ impl RendererApi {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.create_targets(self.color_target.sample_count);
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Physical pixels, with the origin at the top left, to normalized device coordinates,
+    /// with the origin at the center and y going up
+    pub fn screen_to_ndc(&self, position: Point2<f64>) -> Vec2 {
+        let width = self.size.width.max(1) as f64;
+        let height = self.size.height.max(1) as f64;
+        Vec2::new(
+            (2.0 * position.x / width - 1.0) as f32,
+            (1.0 - 2.0 * position.y / height) as f32,
+        )
+    }
+
+    pub fn ndc_to_screen(&self, ndc: Vec2) -> Point2<f64> {
+        Point2 {
+            x: (ndc.x() as f64 + 1.0) * 0.5 * self.size.width as f64,
+            y: (1.0 - ndc.y() as f64) * 0.5 * self.size.height as f64,
+        }
+    }
+
+    /// Logical positions are what the ui uses, physical positions are what the input context uses
+    pub fn logical_to_physical(&self, position: Point2<f64>) -> Point2<f64> {
+        Point2 {
+            x: position.x * self.scale_factor,
+            y: position.y * self.scale_factor,
+        }
+    }
+
+    pub fn physical_to_logical(&self, position: Point2<f64>) -> Point2<f64> {
+        Point2 {
+            x: position.x / self.scale_factor,
+            y: position.y / self.scale_factor,
+        }
+    }
+
+    /// Format of the passes drawing to the frame
+    pub fn frame_format(&self) -> TargetFormat {
+        TargetFormat {
+            color_formats: vec![self.sc_desc.format],
+            depth: true,
+            sample_count: self.color_target.sample_count,
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.color_target.sample_count
+    }
+
+    /// Pipelines drawing to the frame need to be created with the same sample count
+    pub fn set_sample_count(&mut self, sample_count: u32) -> Result<()> {
+        if !SAMPLE_COUNTS.contains(&sample_count) {
+            return Err(anyhow!("Unsupported sample count {}", sample_count));
+        }
+        self.create_targets(sample_count);
+        Ok(())
+    }
+
+    fn create_targets(&mut self, sample_count: u32) {
+        self.depth_texture = Texture::create_depth_texture(
+            &self.device,
+            self.sc_desc.width,
+            self.sc_desc.height,
+            sample_count,
+        );
+        self.color_target = ColorTarget::new(&self.device, &self.sc_desc, sample_count);
     }
 
     pub fn set_v_sync(&mut self, enabled: bool) {
@@ -132,8 +308,39 @@ impl RendererApi {
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
     }
 
+    /// wgpu 0.5 has no `Queue::write_buffer` so the data goes through the staging belt.
+    /// The copy is recorded on the frame encoder which keeps it ordered with the render passes
+    pub fn write_buffer(
+        &mut self,
+        buffer: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        self.staging_belt
+            .write_buffer(&self.device, &mut self.encoder, buffer, offset, data);
+    }
+
+    /// Materials with the same layout share it, so their bind groups work with each other's pipelines
+    pub(crate) fn material_bind_group_layout(
+        &self,
+        has_uniforms: bool,
+        texture_count: usize,
+    ) -> Rc<wgpu::BindGroupLayout> {
+        self.material_layouts
+            .borrow_mut()
+            .entry((has_uniforms, texture_count))
+            .or_insert_with(|| {
+                Rc::new(material::create_bind_group_layout(
+                    &self.device,
+                    has_uniforms,
+                    texture_count,
+                ))
+            })
+            .clone()
+    }
+
     /// If the clear_color is set to none it will use the default renderer clear_color
-    pub fn clear(&mut self, frame: &Frame, clear_color: Option<[f64; 4]>) {
+    pub fn clear(&mut self, target: RenderTarget, clear_color: Option<[f64; 4]>) {
         let [r, g, b, a] = {
             match clear_color {
                 Some(clear_color) => clear_color,
@@ -145,15 +352,35 @@ impl RendererApi {
                 ],
             }
         };
-        let _ = self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: &frame.view,
-                resolve_target: None,
-                load_op: wgpu::LoadOp::Clear,
-                store_op: wgpu::StoreOp::Store,
-                clear_color: wgpu::Color { r, g, b, a },
-            }],
-            depth_stencil_attachment: None,
-        });
+        let _ = self.begin_render_pass(target, wgpu::LoadOp::Clear, wgpu::Color { r, g, b, a });
+    }
+
+    /// Attaches the color and depth attachments of the target,
+    /// `clear_color` is only used when `load_op` is `Clear`
+    pub fn begin_render_pass<'a>(
+        &'a mut self,
+        target: RenderTarget<'a>,
+        load_op: wgpu::LoadOp,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        let (color_attachments, depth_stencil_attachment) = match target {
+            RenderTarget::Frame(frame) => (
+                vec![self
+                    .color_target
+                    .attachment(&frame.view, load_op, clear_color)],
+                Some(self.depth_texture.depth_attachment(load_op)),
+            ),
+            RenderTarget::Framebuffer(framebuffer) => (
+                framebuffer.color_attachment_descriptors(load_op, clear_color),
+                framebuffer
+                    .depth_attachment()
+                    .map(|depth| depth.depth_attachment(load_op)),
+            ),
+        };
+
+        self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &color_attachments,
+            depth_stencil_attachment,
+        })
     }
 }
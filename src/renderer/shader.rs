@@ -1,21 +1,164 @@
-use super::{buffer::VertexBufferLayout, primitives::VertexArray, renderer_api::RendererApi};
+use super::{
+    framebuffer::TargetFormat, pipeline::RenderState, primitives::VertexArray,
+    renderer_api::RendererApi,
+};
 use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_SHADER_ID: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(not(any(feature = "shaderc", feature = "naga", feature = "precompiled-shaders")))]
+compile_error!(
+    "hazel needs the `shaderc` or the `naga` feature to compile shaders, or `precompiled-shaders`"
+);
+
+/// Extra inputs of a shader compilation
+///
+/// `includes` maps the name used in an `#include "name"` directive to the included source.
+/// Shaders using includes need `#extension GL_GOOGLE_include_directive : require`
+#[derive(Default, Clone, Debug)]
+pub struct CompileOptions {
+    pub defines: BTreeMap<String, Option<String>>,
+    pub includes: BTreeMap<String, String>,
+}
+
+impl CompileOptions {
+    pub fn define(mut self, name: &str, value: Option<&str>) -> Self {
+        self.defines
+            .insert(String::from(name), value.map(String::from));
+        self
+    }
+
+    pub fn include(mut self, name: &str, source: &str) -> Self {
+        self.includes
+            .insert(String::from(name), String::from(source));
+        self
+    }
+
+    #[cfg(feature = "shaderc")]
+    fn to_shaderc(&self) -> Result<shaderc::CompileOptions<'static>> {
+        let mut options =
+            shaderc::CompileOptions::new().context("Failed to initialize shaderc options")?;
+
+        for (name, value) in self.defines.iter() {
+            options.add_macro_definition(name, value.as_deref());
+        }
+
+        let includes = self.includes.clone();
+        options.set_include_callback(move |name, _include_type, _source_file, _depth| {
+            includes
+                .get(name)
+                .map(|content| shaderc::ResolvedInclude {
+                    resolved_name: String::from(name),
+                    content: content.clone(),
+                })
+                .ok_or_else(|| format!("Include not found: {}", name))
+        });
+
+        Ok(options)
+    }
+
+    /// naga has no preprocessor hooks so defines and includes are spliced in textually
+    #[cfg(all(feature = "naga", not(feature = "shaderc")))]
+    fn preprocess(&self, src: &str) -> Result<String> {
+        let mut output = String::with_capacity(src.len());
+        let mut defines_written = self.defines.is_empty();
+
+        for line in src.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("#include") {
+                let name = trimmed["#include".len()..]
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '<' || c == '>');
+                let include = self
+                    .includes
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("Include not found: {}", name))?;
+                output.push_str(include);
+                output.push('\n');
+                continue;
+            }
+            if trimmed.starts_with("#extension GL_GOOGLE_include_directive") {
+                continue;
+            }
+
+            output.push_str(line);
+            output.push('\n');
+
+            // defines have to come after #version
+            if !defines_written && trimmed.starts_with("#version") {
+                for (name, value) in self.defines.iter() {
+                    output.push_str(&format!(
+                        "#define {} {}\n",
+                        name,
+                        value.as_deref().unwrap_or_default()
+                    ));
+                }
+                defines_written = true;
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Identifies the compiler that produced a SPIR-V binary, used to invalidate cached shaders
+///
+/// The versions of the crates come from the lock file, see build.rs. Builds without a compiler
+/// load the shaders cached by shaderc, the default compiler.
+#[cfg(any(feature = "shaderc", not(feature = "naga")))]
+pub fn compiler_version() -> String {
+    format!(
+        "shaderc-{}-hazel-{}",
+        env!("HAZEL_SHADERC_VERSION"),
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Identifies the compiler that produced a SPIR-V binary, used to invalidate cached shaders
+///
+/// The version of naga comes from the lock file, see build.rs
+#[cfg(all(feature = "naga", not(feature = "shaderc")))]
+pub fn compiler_version() -> String {
+    format!(
+        "naga-{}-hazel-{}",
+        env!("HAZEL_NAGA_VERSION"),
+        env!("CARGO_PKG_VERSION")
+    )
+}
 
 pub struct Shader {
+    id: u64,
     vertex_data: Vec<u32>,
     fragment_data: Vec<u32>,
+    vertex_entry_point: String,
+    fragment_entry_point: String,
 }
 
 impl Shader {
     pub fn compile(vertex_src: String, fragment_src: String) -> Result<Self> {
+        Self::compile_with_options(vertex_src, fragment_src, &CompileOptions::default())
+    }
+
+    /// Compiles GLSL with shaderc, or with naga when the `shaderc` feature is disabled
+    #[cfg(feature = "shaderc")]
+    pub fn compile_with_options(
+        vertex_src: String,
+        fragment_src: String,
+        options: &CompileOptions,
+    ) -> Result<Self> {
         let mut compiler = shaderc::Compiler::new().expect("Failed to initialize shaderc compiler");
+        let compile_options = options.to_shaderc()?;
         let vs_spirv = compiler
             .compile_into_spirv(
                 &vertex_src,
                 shaderc::ShaderKind::Vertex,
                 "shader.vert",
                 "main",
-                None,
+                Some(&compile_options),
             )
             .context("Failed to compile vert shader")?;
         let fs_spirv = compiler
@@ -24,7 +167,7 @@ impl Shader {
                 shaderc::ShaderKind::Fragment,
                 "shader.frag",
                 "main",
-                None,
+                Some(&compile_options),
             )
             .context("Failed to compile frag shader")?;
 
@@ -33,58 +176,190 @@ impl Shader {
         let fragment_data = wgpu::read_spirv(std::io::Cursor::new(fs_spirv.as_binary_u8()))
             .context("Failed to read fragment shader spirv")?;
 
+        Ok(Self::from_spirv(vertex_data, fragment_data))
+    }
+
+    /// Compiles GLSL with shaderc, or with naga when the `shaderc` feature is disabled
+    #[cfg(all(feature = "naga", not(feature = "shaderc")))]
+    pub fn compile_with_options(
+        vertex_src: String,
+        fragment_src: String,
+        options: &CompileOptions,
+    ) -> Result<Self> {
+        Self::compile_glsl_naga(
+            &options.preprocess(&vertex_src)?,
+            &options.preprocess(&fragment_src)?,
+        )
+    }
+
+    /// Shaders can only be loaded from a `ShaderCache` without a compiler
+    #[cfg(not(any(feature = "shaderc", feature = "naga")))]
+    pub fn compile_with_options(
+        _vertex_src: String,
+        _fragment_src: String,
+        _options: &CompileOptions,
+    ) -> Result<Self> {
+        Err(anyhow::anyhow!(
+            "hazel was built without a shader compiler, only precompiled shaders can be loaded"
+        ))
+    }
+
+    /// Compiles GLSL with naga's pure rust front-end instead of shaderc
+    #[cfg(feature = "naga")]
+    pub fn compile_glsl_naga(vertex_src: &str, fragment_src: &str) -> Result<Self> {
+        let vertex_module = naga::front::glsl::parse_str(
+            vertex_src,
+            String::from("main"),
+            naga::ShaderStage::Vertex,
+        )
+        .context("Failed to parse vert shader")?;
+        let fragment_module = naga::front::glsl::parse_str(
+            fragment_src,
+            String::from("main"),
+            naga::ShaderStage::Fragment,
+        )
+        .context("Failed to parse frag shader")?;
+
+        Ok(Self::from_spirv(
+            naga_to_spirv(&vertex_module),
+            naga_to_spirv(&fragment_module),
+        ))
+    }
+
+    /// Compiles a WGSL module containing both the vertex and the fragment entry points
+    #[cfg(feature = "naga")]
+    pub fn compile_wgsl(
+        src: &str,
+        vertex_entry_point: &str,
+        fragment_entry_point: &str,
+    ) -> Result<Self> {
+        // the parse error borrows the source so it can't be wrapped as is
+        let module = naga::front::wgsl::parse_str(src)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .context("Failed to parse wgsl shader")?;
+
+        for (stage, name) in &[
+            (naga::ShaderStage::Vertex, vertex_entry_point),
+            (naga::ShaderStage::Fragment, fragment_entry_point),
+        ] {
+            if !module
+                .entry_points
+                .iter()
+                .any(|entry_point| entry_point.stage == *stage && entry_point.name == *name)
+            {
+                return Err(anyhow::anyhow!(
+                    "Entry point {} not found for {:?}",
+                    name,
+                    stage
+                ));
+            }
+        }
+
+        let spirv = naga_to_spirv(&module);
+
         Ok(Self {
+            id: NEXT_SHADER_ID.fetch_add(1, Ordering::Relaxed),
+            vertex_data: spirv.clone(),
+            fragment_data: spirv,
+            vertex_entry_point: String::from(vertex_entry_point),
+            fragment_entry_point: String::from(fragment_entry_point),
+        })
+    }
+
+    /// Creates a shader from already compiled SPIR-V words, both entry points are `main`
+    pub fn from_spirv(vertex_data: Vec<u32>, fragment_data: Vec<u32>) -> Self {
+        Self {
+            id: NEXT_SHADER_ID.fetch_add(1, Ordering::Relaxed),
             vertex_data,
             fragment_data,
-        })
+            vertex_entry_point: String::from("main"),
+            fragment_entry_point: String::from("main"),
+        }
+    }
+
+    /// Unique for the lifetime of the application, the materials of a shader share its pipelines
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn vertex_spirv(&self) -> &[u32] {
+        &self.vertex_data
+    }
+
+    pub fn fragment_spirv(&self) -> &[u32] {
+        &self.fragment_data
+    }
+
+    pub fn create_pipeline(
+        &self,
+        renderer: &RendererApi,
+        vertex_array: &VertexArray,
+        target: &TargetFormat,
+        state: RenderState,
+    ) -> wgpu::RenderPipeline {
+        self.create_pipeline_with_layout(
+            renderer,
+            &renderer.pipeline_layout,
+            vertex_array,
+            target,
+            state,
+        )
     }
 
-    pub fn create_pipeline<T>(
+    /// Same as create_pipeline but for shaders using bind groups
+    pub fn create_pipeline_with_layout(
         &self,
         renderer: &RendererApi,
-        vertex_array: &VertexArray<T>,
-        samples: u32,
-    ) -> wgpu::RenderPipeline
-    where
-        T: VertexBufferLayout + bytemuck::Pod + bytemuck::Zeroable,
-    {
+        layout: &wgpu::PipelineLayout,
+        vertex_array: &VertexArray,
+        target: &TargetFormat,
+        state: RenderState,
+    ) -> wgpu::RenderPipeline {
+        let color_states = target
+            .color_formats
+            .iter()
+            .map(|format| wgpu::ColorStateDescriptor {
+                format: *format,
+                color_blend: state.blend.color_blend(),
+                alpha_blend: state.blend.alpha_blend(),
+                write_mask: wgpu::ColorWrite::ALL,
+            })
+            .collect::<Vec<_>>();
         let vs_module = renderer.device.create_shader_module(&self.vertex_data);
         let fs_module = renderer.device.create_shader_module(&self.fragment_data);
 
         renderer
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                layout: &renderer.pipeline_layout,
+                layout,
                 vertex_stage: wgpu::ProgrammableStageDescriptor {
                     module: &vs_module,
-                    entry_point: "main",
+                    entry_point: &self.vertex_entry_point,
                 },
                 fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
                     module: &fs_module,
-                    entry_point: "main",
-                }),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Back,
-                    depth_bias: 0,
-                    depth_bias_slope_scale: 0.0,
-                    depth_bias_clamp: 0.0,
+                    entry_point: &self.fragment_entry_point,
                 }),
-                color_states: &[wgpu::ColorStateDescriptor {
-                    format: renderer.sc_desc.format,
-                    color_blend: wgpu::BlendDescriptor::REPLACE,
-                    alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                depth_stencil_state: None,
+                rasterization_state: Some(state.rasterization_state()),
+                color_states: &color_states,
+                primitive_topology: state.topology,
+                depth_stencil_state: if target.depth {
+                    Some(state.depth.descriptor())
+                } else {
+                    None
+                },
                 vertex_state: wgpu::VertexStateDescriptor {
-                    index_format: vertex_array.index_buffer.format,
-                    vertex_buffers: &[vertex_array.vertex_buffer.descriptor()],
+                    index_format: vertex_array.index_format(),
+                    vertex_buffers: &vertex_array.descriptors(),
                 },
-                sample_count: samples,
+                sample_count: target.sample_count,
                 sample_mask: !0,
                 alpha_to_coverage_enabled: false,
             })
     }
 }
+
+#[cfg(feature = "naga")]
+fn naga_to_spirv(module: &naga::Module) -> Vec<u32> {
+    naga::back::spv::Writer::new(&module.header, naga::back::spv::WriterFlags::NONE).write(module)
+}
@@ -0,0 +1,237 @@
+use super::shader::{compiler_version, CompileOptions, Shader};
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderCacheMode {
+    /// Load from the cache when possible, otherwise compile the shader and store the result
+    ReadWrite,
+    /// Only load precompiled SPIR-V, the compiler is never invoked and a missing entry is an error
+    PrecompiledOnly,
+}
+
+impl Default for ShaderCacheMode {
+    /// The `precompiled-shaders` feature is meant for release builds that ship the cache directory
+    fn default() -> Self {
+        if cfg!(feature = "precompiled-shaders") {
+            ShaderCacheMode::PrecompiledOnly
+        } else {
+            ShaderCacheMode::ReadWrite
+        }
+    }
+}
+
+/// Stores compiled SPIR-V on disk so shaderc only runs when a shader actually changed
+///
+/// Entries are keyed by a hash of the sources, includes, defines and compiler version
+/// Example usage:
+/// ```rust,ignore
+///     let cache = ShaderCache::new("shader_cache");
+///     let shader = cache.load(vertex_src, fragment_src, &CompileOptions::default())?;
+/// ```
+pub struct ShaderCache {
+    dir: PathBuf,
+    pub mode: ShaderCacheMode,
+}
+
+impl ShaderCache {
+    /// The `shader_cache` directory next to the executable, found whatever the working directory
+    /// is. Falls back to the working directory when the path of the executable is unknown
+    pub fn default_dir() -> PathBuf {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("shader_cache")))
+            .unwrap_or_else(|| PathBuf::from("shader_cache"))
+    }
+
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            mode: ShaderCacheMode::default(),
+        }
+    }
+
+    pub fn with_mode<P: Into<PathBuf>>(dir: P, mode: ShaderCacheMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn load(
+        &self,
+        vertex_src: &str,
+        fragment_src: &str,
+        options: &CompileOptions,
+    ) -> Result<Shader> {
+        let key = cache_key(&compiler_version(), vertex_src, fragment_src, options);
+
+        match self.read(key) {
+            Ok(Some(shader)) => {
+                log::trace!("Shader {:016x} loaded from cache", key);
+                return Ok(shader);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Invalid shader cache entry {:016x}: {:?}", key, e),
+        }
+
+        if self.mode == ShaderCacheMode::PrecompiledOnly {
+            return Err(anyhow!(
+                "No precompiled shader found for key {:016x} in {:?}",
+                key,
+                self.dir
+            ));
+        }
+
+        let shader = Shader::compile_with_options(
+            String::from(vertex_src),
+            String::from(fragment_src),
+            options,
+        )?;
+
+        if let Err(e) = self.write(key, &shader) {
+            log::warn!("Failed to write shader cache entry {:016x}: {:?}", key, e);
+        }
+
+        Ok(shader)
+    }
+
+    fn entry_paths(&self, key: u64) -> (PathBuf, PathBuf) {
+        (
+            self.dir.join(format!("{:016x}.vert.spv", key)),
+            self.dir.join(format!("{:016x}.frag.spv", key)),
+        )
+    }
+
+    fn read(&self, key: u64) -> Result<Option<Shader>> {
+        let (vert_path, frag_path) = self.entry_paths(key);
+        if !vert_path.exists() || !frag_path.exists() {
+            return Ok(None);
+        }
+
+        let read = |path: &Path| -> Result<Vec<u32>> {
+            let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+            wgpu::read_spirv(std::io::Cursor::new(bytes))
+                .with_context(|| format!("Failed to read spirv from {:?}", path))
+        };
+
+        Ok(Some(Shader::from_spirv(
+            read(&vert_path)?,
+            read(&frag_path)?,
+        )))
+    }
+
+    fn write(&self, key: u64, shader: &Shader) -> Result<()> {
+        fs::create_dir_all(&self.dir).context("Failed to create shader cache directory")?;
+
+        let (vert_path, frag_path) = self.entry_paths(key);
+        write_atomic(&vert_path, bytemuck::cast_slice(shader.vertex_spirv()))?;
+        write_atomic(&frag_path, bytemuck::cast_slice(shader.fragment_spirv()))?;
+
+        Ok(())
+    }
+}
+
+/// Writes to a temporary file first so a crash never leaves a truncated entry behind
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes).with_context(|| format!("Failed to write {:?}", tmp_path))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to rename {:?}", tmp_path))
+}
+
+/// FNV-1a, std's DefaultHasher is not guaranteed to be stable between rust releases
+fn cache_key(
+    compiler: &str,
+    vertex_src: &str,
+    fragment_src: &str,
+    options: &CompileOptions,
+) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut write = |bytes: &[u8]| {
+        // length prefix so ("ab", "c") and ("a", "bc") don't collide
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    write(compiler.as_bytes());
+    write(vertex_src.as_bytes());
+    write(fragment_src.as_bytes());
+    for (name, value) in options.defines.iter() {
+        write(name.as_bytes());
+        write(&[value.is_some() as u8]);
+        write(value.as_deref().unwrap_or_default().as_bytes());
+    }
+    for (name, source) in options.includes.iter() {
+        write(name.as_bytes());
+        write(source.as_bytes());
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_changes_with_every_input() {
+        let options = CompileOptions::default()
+            .define("LIGHTS", Some("4"))
+            .include("common.glsl", "float x;");
+        let key = cache_key("shaderc-0.6.2", "vertex", "fragment", &options);
+        assert_eq!(
+            key,
+            cache_key("shaderc-0.6.2", "vertex", "fragment", &options)
+        );
+
+        let others = [
+            cache_key("shaderc-0.6.3", "vertex", "fragment", &options),
+            cache_key("shaderc-0.6.2", "vertex 2", "fragment", &options),
+            cache_key("shaderc-0.6.2", "vertex", "fragment 2", &options),
+            // a source moved from one stage to the other
+            cache_key("shaderc-0.6.2", "vertexf", "ragment", &options),
+            cache_key(
+                "shaderc-0.6.2",
+                "vertex",
+                "fragment",
+                &options.clone().define("LIGHTS", Some("8")),
+            ),
+            cache_key(
+                "shaderc-0.6.2",
+                "vertex",
+                "fragment",
+                &options.clone().define("LIGHTS", None),
+            ),
+            cache_key(
+                "shaderc-0.6.2",
+                "vertex",
+                "fragment",
+                &options.clone().define("SHADOWS", None),
+            ),
+            cache_key(
+                "shaderc-0.6.2",
+                "vertex",
+                "fragment",
+                &options.clone().include("common.glsl", "float y;"),
+            ),
+            cache_key(
+                "shaderc-0.6.2",
+                "vertex",
+                "fragment",
+                &options.clone().include("lighting.glsl", ""),
+            ),
+        ];
+        for other in others.iter() {
+            assert_ne!(key, *other);
+        }
+    }
+}
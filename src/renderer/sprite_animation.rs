@@ -0,0 +1,332 @@
+use super::{renderer_api::RendererApi, sprite_sheet::SpriteSheet, sub_texture::SubTexture2D};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, rc::Rc, time::Duration};
+
+/// What happens when a clip reaches its last frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    /// Goes back and forth, the first and last frames aren't repeated
+    PingPong,
+    /// Stays on the last frame
+    Once,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    /// Index of the sprite in the sprite sheet
+    pub sprite: usize,
+    pub duration: Duration,
+    /// Returned by `AnimatedSprite::update` when the frame starts
+    pub event: Option<String>,
+}
+
+/// A named sequence of frames, like "walk" or "attack"
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode,
+}
+
+impl AnimationClip {
+    pub fn new(name: &str, frames: Vec<AnimationFrame>, mode: PlayMode) -> Self {
+        Self {
+            name: String::from(name),
+            frames,
+            mode,
+        }
+    }
+
+    /// Consecutive sprites of the sheet, each shown for `frame_duration`
+    pub fn from_range(
+        name: &str,
+        sprites: std::ops::Range<usize>,
+        frame_duration: Duration,
+        mode: PlayMode,
+    ) -> Self {
+        let frames = sprites
+            .map(|sprite| AnimationFrame {
+                sprite,
+                duration: frame_duration,
+                event: None,
+            })
+            .collect();
+        Self::new(name, frames, mode)
+    }
+
+    /// Fires `event` when the frame at `index` starts, ignored when there's no such frame
+    pub fn with_event(mut self, index: usize, event: &str) -> Self {
+        if let Some(frame) = self.frames.get_mut(index) {
+            frame.event = Some(String::from(event));
+        }
+        self
+    }
+
+    /// Duration of one pass through the frames
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// A sprite going through the frames of its clips over time
+///
+/// ```rust,ignore
+///     let mut player = AnimatedSprite::from_aseprite(&mut app.renderer.api, "assets/player.json")?;
+///     player.play("run")?;
+///     // in on_update
+///     for event in player.update(app.delta_t) {
+///         if event == "footstep" { ... }
+///     }
+///     // in on_render
+///     app.renderer_2d.draw_sprite(position, size, player.sprite().unwrap(), Vec4::one());
+/// ```
+pub struct AnimatedSprite {
+    pub sheet: Rc<SpriteSheet>,
+    /// Multiplies the time, 2.0 plays twice as fast
+    pub speed: f32,
+    pub paused: bool,
+    clips: HashMap<String, AnimationClip>,
+    current: Option<String>,
+    frame: usize,
+    /// Time spent on the current frame
+    time: Duration,
+    /// Direction of a ping pong clip
+    backwards: bool,
+    finished: bool,
+    /// Events of frames started outside of `update`, like the first frame of a clip
+    pending_events: Vec<String>,
+}
+
+impl AnimatedSprite {
+    pub fn new(sheet: Rc<SpriteSheet>) -> Self {
+        Self {
+            sheet,
+            speed: 1.0,
+            paused: false,
+            clips: HashMap::new(),
+            current: None,
+            frame: 0,
+            time: Duration::default(),
+            backwards: false,
+            finished: false,
+            pending_events: vec![],
+        }
+    }
+
+    /// Loads an Aseprite JSON export, with one clip per tag.
+    /// Without tags every frame is in a looping clip named after the file
+    pub fn from_aseprite<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let sheet = Rc::new(SpriteSheet::from_json_file(api, path)?);
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read sprite sheet {:?}", path))?;
+        let mut clips = aseprite_clips(&json)?;
+        if clips.is_empty() {
+            let name = path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or("default");
+            clips.push(AnimationClip::new(
+                name,
+                aseprite_frames(&json)?,
+                PlayMode::Loop,
+            ));
+        }
+
+        let mut sprite = Self::new(sheet);
+        for clip in clips {
+            sprite.add_clip(clip);
+        }
+        Ok(sprite)
+    }
+
+    /// Replaces a clip with the same name
+    pub fn add_clip(&mut self, clip: AnimationClip) {
+        self.clips.insert(clip.name.clone(), clip);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    pub fn clip_names(&self) -> impl Iterator<Item = &str> {
+        self.clips.keys().map(|name| name.as_str())
+    }
+
+    /// Keeps going when the clip is already playing, use `restart` to start it over
+    pub fn play(&mut self, name: &str) -> Result<()> {
+        if self.current.as_deref() == Some(name) {
+            return Ok(());
+        }
+        if !self.clips.contains_key(name) {
+            return Err(anyhow!("Animation has no clip named {}", name));
+        }
+        self.current = Some(String::from(name));
+        self.restart();
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.time = Duration::default();
+        self.backwards = false;
+        self.finished = false;
+        self.pending_events.clear();
+        if let Some(event) = self.current_frame().and_then(|frame| frame.event.clone()) {
+            self.pending_events.push(event);
+        }
+    }
+
+    /// Moves the animation forward and returns the events of the frames it went through
+    pub fn update(&mut self, delta_t: Duration) -> Vec<String> {
+        let mut events = std::mem::take(&mut self.pending_events);
+        if self.paused || self.finished {
+            return events;
+        }
+        let clips = &self.clips;
+        let clip = match self.current.as_ref().and_then(|name| clips.get(name)) {
+            Some(clip) if !clip.frames.is_empty() => clip,
+            _ => return events,
+        };
+
+        self.time += delta_t.mul_f32(self.speed.max(0.0));
+        loop {
+            // a frame lasting 0 would never let the loop end
+            let duration = clip.frames[self.frame]
+                .duration
+                .max(Duration::from_millis(1));
+            if self.time < duration {
+                break;
+            }
+            self.time -= duration;
+
+            let last = clip.frames.len() - 1;
+            self.frame = match clip.mode {
+                PlayMode::Loop => (self.frame + 1) % clip.frames.len(),
+                PlayMode::Once if self.frame == last => {
+                    self.finished = true;
+                    self.time = Duration::default();
+                    break;
+                }
+                PlayMode::Once => self.frame + 1,
+                PlayMode::PingPong if last == 0 => 0,
+                PlayMode::PingPong => {
+                    if (self.backwards && self.frame == 0)
+                        || (!self.backwards && self.frame == last)
+                    {
+                        self.backwards = !self.backwards;
+                    }
+                    if self.backwards {
+                        self.frame - 1
+                    } else {
+                        self.frame + 1
+                    }
+                }
+            };
+            if let Some(event) = &clip.frames[self.frame].event {
+                events.push(event.clone());
+            }
+        }
+        events
+    }
+
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Index of the frame in the current clip
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.current
+            .as_ref()
+            .and_then(|name| self.clips.get(name))
+            .and_then(|clip| clip.frames.get(self.frame))
+    }
+
+    /// A clip played once is finished on its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Sprite of the current frame
+    pub fn sprite(&self) -> Option<&SubTexture2D> {
+        self.current_frame()
+            .and_then(|frame| self.sheet.sprite(frame.sprite))
+    }
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteData {
+    frames: serde_json::Value,
+    meta: AsepriteMeta,
+}
+
+/// Every frame of the export in order, with its duration
+fn aseprite_frames(json: &str) -> Result<Vec<AnimationFrame>> {
+    let data: AsepriteData = serde_json::from_str(json).context("Invalid Aseprite sprite sheet")?;
+    let frames = match data.frames {
+        serde_json::Value::Array(frames) => frames,
+        serde_json::Value::Object(frames) => frames.into_iter().map(|(_, frame)| frame).collect(),
+        _ => return Err(anyhow!("Aseprite frames should be an array or an object")),
+    };
+    Ok(frames
+        .iter()
+        .enumerate()
+        .map(|(sprite, frame)| AnimationFrame {
+            sprite,
+            duration: Duration::from_millis(frame["duration"].as_u64().unwrap_or(100)),
+            event: None,
+        })
+        .collect())
+}
+
+/// One clip per tag, tags loop and their direction is kept
+fn aseprite_clips(json: &str) -> Result<Vec<AnimationClip>> {
+    let data: AsepriteData = serde_json::from_str(json).context("Invalid Aseprite sprite sheet")?;
+    let frames = aseprite_frames(json)?;
+    data.meta
+        .frame_tags
+        .iter()
+        .map(|tag| {
+            let mut clip_frames = frames
+                .get(tag.from..=tag.to)
+                .ok_or_else(|| anyhow!("Tag {} is outside of the frames", tag.name))?
+                .to_vec();
+            let mode = match tag.direction.as_str() {
+                "pingpong" => PlayMode::PingPong,
+                "reverse" => {
+                    clip_frames.reverse();
+                    PlayMode::Loop
+                }
+                // older versions don't have pingpong_reverse, it starts from the end
+                "pingpong_reverse" => {
+                    clip_frames.reverse();
+                    PlayMode::PingPong
+                }
+                _ => PlayMode::Loop,
+            };
+            Ok(AnimationClip::new(&tag.name, clip_frames, mode))
+        })
+        .collect()
+}
@@ -0,0 +1,203 @@
+use super::{
+    renderer_api::RendererApi,
+    sub_texture::{SubTexture2D, Trim},
+    texture::Texture,
+};
+use anyhow::{anyhow, Context, Result};
+use glam::Vec2;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, rc::Rc};
+
+#[derive(Deserialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct Size {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct FrameData {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    /// Where the frame is in the untrimmed sprite
+    #[serde(default, rename = "spriteSourceSize")]
+    sprite_source_size: Option<Rect>,
+    #[serde(default, rename = "sourceSize")]
+    source_size: Option<Size>,
+}
+
+/// TexturePacker exports the frames as an object or as an array, Aseprite can do both too
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Array(Vec<FrameData>),
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    image: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AtlasData {
+    frames: Frames,
+    meta: Option<Meta>,
+}
+
+/// Sprites sharing a texture, drawn by the 2D renderer in a single draw call
+///
+/// ```rust,ignore
+///     let sheet = SpriteSheet::from_grid(texture, 16, 16);
+///     let grass = sheet.sprite(3).unwrap();
+///     let atlas = SpriteSheet::from_json_file(&mut app.renderer.api, "assets/atlas.json")?;
+///     let player = atlas.get("player_idle_0").unwrap();
+/// ```
+pub struct SpriteSheet {
+    pub texture: Rc<Texture>,
+    sprites: Vec<SubTexture2D>,
+    names: HashMap<String, usize>,
+}
+
+impl SpriteSheet {
+    pub fn new(texture: Rc<Texture>) -> Self {
+        Self {
+            texture,
+            sprites: vec![],
+            names: HashMap::new(),
+        }
+    }
+
+    /// One sprite per cell, from left to right and top to bottom.
+    /// Pixels on the right and bottom that don't fill a cell are ignored
+    pub fn from_grid(texture: Rc<Texture>, cell_width: u32, cell_height: u32) -> Self {
+        let mut sheet = Self::new(texture.clone());
+        let columns = texture.width / cell_width.max(1);
+        let rows = texture.height / cell_height.max(1);
+        for row in 0..rows {
+            for column in 0..columns {
+                sheet.sprites.push(SubTexture2D::from_pixels(
+                    texture.clone(),
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                ));
+            }
+        }
+        sheet
+    }
+
+    /// Loads a TexturePacker or Aseprite JSON description, with the image it references next to it
+    pub fn from_json_file<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read sprite sheet {:?}", path))?;
+        let image = parse(&json)?
+            .meta
+            .and_then(|meta| meta.image)
+            .ok_or_else(|| anyhow!("Sprite sheet {:?} doesn't name its image", path))?;
+        let image_path = path.parent().unwrap_or_else(|| Path::new("")).join(image);
+        let texture = Rc::new(Texture::from_file(api, image_path)?);
+        Self::from_json(texture, &json)
+    }
+
+    /// Frames of a TexturePacker or Aseprite JSON description, in the order they are listed.
+    /// Trimmed frames keep the size of the untrimmed sprite, the renderer draws the frame where it
+    /// was cut from
+    pub fn from_json(texture: Rc<Texture>, json: &str) -> Result<Self> {
+        let frames = match parse(json)?.frames {
+            Frames::Array(frames) => frames,
+            Frames::Hash(frames) => frames
+                .into_iter()
+                .map(|(name, frame)| {
+                    let mut frame: FrameData = serde_json::from_value(frame)
+                        .with_context(|| format!("Invalid frame {}", name))?;
+                    frame.filename = Some(name);
+                    Ok(frame)
+                })
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        let mut sheet = Self::new(texture.clone());
+        for frame in frames {
+            let Rect { x, y, w, h } = frame.frame;
+            // the size of a rotated frame is the size of the sprite, not of the region
+            let (width, height) = if frame.rotated { (h, w) } else { (w, h) };
+            let mut sprite = SubTexture2D::from_pixels(texture.clone(), x, y, width, height);
+            sprite.rotated = frame.rotated;
+            if let (Some(trimmed), Some(source)) = (frame.sprite_source_size, frame.source_size) {
+                if (trimmed.x, trimmed.y, trimmed.w, trimmed.h) != (0, 0, source.w, source.h) {
+                    sprite.trim = Some(Trim {
+                        offset: Vec2::new(trimmed.x as f32, trimmed.y as f32),
+                        source_size: Vec2::new(source.w as f32, source.h as f32),
+                    });
+                }
+            }
+            match frame.filename {
+                Some(name) => sheet.insert(&name, sprite),
+                None => sheet.push(sprite),
+            };
+        }
+        Ok(sheet)
+    }
+
+    /// Returns the index of the sprite
+    pub fn push(&mut self, sprite: SubTexture2D) -> usize {
+        self.sprites.push(sprite);
+        self.sprites.len() - 1
+    }
+
+    /// Returns the index of the sprite, a sprite with the same name is replaced
+    pub fn insert(&mut self, name: &str, sprite: SubTexture2D) -> usize {
+        match self.names.get(name) {
+            Some(&index) => {
+                self.sprites[index] = sprite;
+                index
+            }
+            None => {
+                let index = self.push(sprite);
+                self.names.insert(String::from(name), index);
+                index
+            }
+        }
+    }
+
+    pub fn sprite(&self, index: usize) -> Option<&SubTexture2D> {
+        self.sprites.get(index)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SubTexture2D> {
+        self.index_of(name).and_then(|index| self.sprite(index))
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    pub fn sprites(&self) -> &[SubTexture2D] {
+        &self.sprites
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+}
+
+fn parse(json: &str) -> Result<AtlasData> {
+    serde_json::from_str(json).context("Invalid sprite sheet description")
+}
@@ -0,0 +1,154 @@
+use futures::{future::FutureExt, Future};
+use std::pin::Pin;
+
+type MapFuture =
+    Pin<Box<dyn Future<Output = Result<wgpu::BufferWriteMapping, wgpu::BufferAsyncErr>>>>;
+
+struct Chunk {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    offset: wgpu::BufferAddress,
+}
+
+/// Reuses a set of mapped staging buffers to upload data without allocating every frame
+///
+/// wgpu 0.5 has no `Queue::write_buffer` or `util::StagingBelt`, this is the same idea.
+/// The data is copied in a mapped chunk and the copy to the destination is recorded on the encoder.
+/// `finish` has to be called before submitting the encoder and `recall` after,
+/// a chunk is reused once the gpu is done with it.
+pub struct StagingBelt {
+    chunk_size: wgpu::BufferAddress,
+    active_chunks: Vec<(Chunk, wgpu::BufferWriteMapping)>,
+    closed_chunks: Vec<Chunk>,
+    free_chunks: Vec<(Chunk, wgpu::BufferWriteMapping)>,
+    recalled_chunks: Vec<(Chunk, MapFuture)>,
+}
+
+impl StagingBelt {
+    pub fn new(chunk_size: wgpu::BufferAddress) -> Self {
+        Self {
+            chunk_size,
+            active_chunks: vec![],
+            closed_chunks: vec![],
+            free_chunks: vec![],
+            recalled_chunks: vec![],
+        }
+    }
+
+    /// `offset` has to be a multiple of 4. Copies are padded to 4 bytes, up to 3 bytes after the
+    /// data are overwritten with zeros and the target needs room for them
+    pub fn write_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        if data.is_empty() {
+            return;
+        }
+        assert!(offset % 4 == 0, "Buffer copies need to start on 4 bytes");
+        // copies need a size that's a multiple of 4 too
+        let size = (data.len() as wgpu::BufferAddress + 3) & !3;
+
+        self.poll_recalled(device);
+
+        let index = match self
+            .active_chunks
+            .iter()
+            .position(|(chunk, _)| chunk.offset + size <= chunk.size)
+        {
+            Some(index) => index,
+            None => {
+                let chunk = match self
+                    .free_chunks
+                    .iter()
+                    .position(|(chunk, _)| size <= chunk.size)
+                {
+                    Some(index) => self.free_chunks.swap_remove(index),
+                    None => Self::create_chunk(device, size.max(self.chunk_size)),
+                };
+                self.active_chunks.push(chunk);
+                self.active_chunks.len() - 1
+            }
+        };
+
+        let (chunk, mapping) = &mut self.active_chunks[index];
+        let start = chunk.offset as usize;
+        let slice = &mut mapping.as_slice()[start..start + size as usize];
+        slice[..data.len()].copy_from_slice(data);
+        for byte in slice[data.len()..].iter_mut() {
+            *byte = 0;
+        }
+        encoder.copy_buffer_to_buffer(&chunk.buffer, chunk.offset, target, offset, size);
+        chunk.offset += size;
+    }
+
+    /// Unmaps the chunks used this frame, needs to be called before submitting
+    pub fn finish(&mut self) {
+        // dropping the mapping unmaps the buffer
+        self.closed_chunks
+            .extend(self.active_chunks.drain(..).map(|(chunk, _)| chunk));
+    }
+
+    /// Starts mapping the chunks used this frame again, needs to be called after submitting
+    pub fn recall(&mut self) {
+        for chunk in self.closed_chunks.drain(..) {
+            let future: MapFuture = Box::pin(chunk.buffer.map_write(0, chunk.size));
+            self.recalled_chunks.push((chunk, future));
+        }
+    }
+
+    fn poll_recalled(&mut self, device: &wgpu::Device) {
+        if self.recalled_chunks.is_empty() {
+            return;
+        }
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut i = 0;
+        while i < self.recalled_chunks.len() {
+            match (&mut self.recalled_chunks[i].1).now_or_never() {
+                Some(Ok(mapping)) => {
+                    let (mut chunk, _) = self.recalled_chunks.swap_remove(i);
+                    chunk.offset = 0;
+                    self.free_chunks.push((chunk, mapping));
+                }
+                Some(Err(_)) => {
+                    log::error!("Failed to map staging buffer");
+                    self.recalled_chunks.swap_remove(i);
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    fn create_chunk(
+        device: &wgpu::Device,
+        size: wgpu::BufferAddress,
+    ) -> (Chunk, wgpu::BufferWriteMapping) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Staging chunk"),
+            size,
+            usage: wgpu::BufferUsage::MAP_WRITE | wgpu::BufferUsage::COPY_SRC,
+        });
+
+        // a new buffer isn't used by the gpu so this resolves on the first poll
+        let mut future = Box::pin(buffer.map_write(0, size));
+        let mapping = loop {
+            device.poll(wgpu::Maintain::Wait);
+            if let Some(result) = (&mut future).now_or_never() {
+                break result.expect("Failed to map new staging buffer");
+            }
+        };
+
+        (
+            Chunk {
+                buffer,
+                size,
+                offset: 0,
+            },
+            mapping,
+        )
+    }
+}
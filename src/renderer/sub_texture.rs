@@ -0,0 +1,115 @@
+use super::texture::Texture;
+use glam::Vec2;
+use std::rc::Rc;
+
+/// Where a trimmed region goes in the sprite it was cut from
+#[derive(Clone, Copy, Debug)]
+pub struct Trim {
+    /// Top left of the region in the untrimmed sprite, in pixels
+    pub offset: Vec2,
+    /// Size of the untrimmed sprite in pixels
+    pub source_size: Vec2,
+}
+
+/// A region of a texture, like a sprite of a sprite sheet
+///
+/// Texture coordinates go from the top left of the texture at 0, 0 to the bottom right at 1, 1.
+#[derive(Clone)]
+pub struct SubTexture2D {
+    pub texture: Rc<Texture>,
+    /// Top left of the region
+    pub min: Vec2,
+    /// Bottom right of the region
+    pub max: Vec2,
+    /// The region is stored turned 90 degrees clockwise, like packers do to save space
+    pub rotated: bool,
+    /// Packers cut the transparent borders of sprites, the region is drawn where it was in the
+    /// untrimmed sprite
+    pub trim: Option<Trim>,
+}
+
+impl SubTexture2D {
+    pub fn new(texture: Rc<Texture>, min: Vec2, max: Vec2) -> Self {
+        Self {
+            texture,
+            min,
+            max,
+            rotated: false,
+            trim: None,
+        }
+    }
+
+    /// The whole texture
+    pub fn from_texture(texture: Rc<Texture>) -> Self {
+        Self::new(texture, Vec2::zero(), Vec2::one())
+    }
+
+    /// Region in pixels, from the top left of the texture
+    pub fn from_pixels(texture: Rc<Texture>, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let size = Vec2::new(texture.width as f32, texture.height as f32);
+        let min = Vec2::new(x as f32, y as f32) / size;
+        let max = Vec2::new((x + width) as f32, (y + height) as f32) / size;
+        Self::new(texture, min, max)
+    }
+
+    /// Cell of a grid, `coords` is the column and the row from the top left.
+    /// `sprite_size` is in cells, for sprites spanning more than one cell
+    pub fn from_coords(
+        texture: Rc<Texture>,
+        coords: Vec2,
+        cell_size: Vec2,
+        sprite_size: Vec2,
+    ) -> Self {
+        let size = Vec2::new(texture.width as f32, texture.height as f32);
+        let min = coords * cell_size / size;
+        let max = (coords + sprite_size) * cell_size / size;
+        Self::new(texture, min, max)
+    }
+
+    /// Size of the sprite in pixels, as it's drawn
+    pub fn size(&self) -> Vec2 {
+        let size = (self.max - self.min)
+            * Vec2::new(self.texture.width as f32, self.texture.height as f32);
+        if self.rotated {
+            Vec2::new(size.y(), size.x())
+        } else {
+            size
+        }
+    }
+
+    /// Size of the untrimmed sprite in pixels
+    pub fn source_size(&self) -> Vec2 {
+        match self.trim {
+            Some(trim) => trim.source_size,
+            None => self.size(),
+        }
+    }
+
+    /// Center and size of the region in a quad of `size` showing the untrimmed sprite.
+    /// The center is relative to the center of the quad, y goes up
+    pub fn trimmed_quad(&self, size: Vec2) -> (Vec2, Vec2) {
+        let trim = match self.trim {
+            Some(trim) => trim,
+            None => return (Vec2::zero(), size),
+        };
+        let scale = size / trim.source_size;
+        let region = self.size();
+        let center = (trim.offset + region / 2.0 - trim.source_size / 2.0) * scale;
+        (Vec2::new(center.x(), -center.y()), region * scale)
+    }
+
+    /// Coordinates of the bottom left, bottom right, top right and top left corners of the sprite
+    pub fn tex_coords(&self) -> [Vec2; 4] {
+        let (min, max) = (self.min, self.max);
+        let top_left = min;
+        let top_right = Vec2::new(max.x(), min.y());
+        let bottom_right = max;
+        let bottom_left = Vec2::new(min.x(), max.y());
+        if self.rotated {
+            // the top left of the sprite is at the top right of the region
+            [top_left, bottom_left, bottom_right, top_right]
+        } else {
+            [bottom_left, bottom_right, top_right, top_left]
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use super::renderer_api::RendererApi;
+use anyhow::{Context, Result};
+use image::GenericImageView;
+use std::path::Path;
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    pub fn from_file<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let image =
+            image::open(path).with_context(|| format!("Failed to load texture {:?}", path))?;
+
+        Ok(Self::from_image(api, &image, path.to_str()))
+    }
+
+    pub fn from_bytes(api: &mut RendererApi, bytes: &[u8], label: Option<&str>) -> Result<Self> {
+        let image = image::load_from_memory(bytes).context("Failed to decode texture")?;
+
+        Ok(Self::from_image(api, &image, label))
+    }
+
+    pub fn from_image(
+        api: &mut RendererApi,
+        image: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Self {
+        let (width, height) = image.dimensions();
+        Self::from_rgba(api, width, height, &image.to_rgba(), label)
+    }
+
+    /// 1x1 white texture, useful as a default when a texture slot isn't used
+    pub fn white(api: &mut RendererApi) -> Self {
+        Self::from_rgba(api, 1, 1, &[255; 4], Some("White texture"))
+    }
+
+    /// `data` is expected to be tightly packed 8 bit srgb rgba
+    pub fn from_rgba(
+        api: &mut RendererApi,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let texture = api.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        let staging_buffer = api
+            .device
+            .create_buffer_with_data(data, wgpu::BufferUsage::COPY_SRC);
+        api.encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &staging_buffer,
+                offset: 0,
+                bytes_per_row: 4 * width,
+                rows_per_image: height,
+            },
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            size,
+        );
+
+        Self {
+            view: texture.create_default_view(),
+            sampler: create_sampler(&api.device, wgpu::FilterMode::Linear),
+            texture,
+            width,
+            height,
+        }
+    }
+
+    /// Can be drawn to and sampled, used for framebuffer attachments
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+
+        Self {
+            view: texture.create_default_view(),
+            sampler: create_sampler(device, wgpu::FilterMode::Linear),
+            texture,
+            width,
+            height,
+        }
+    }
+
+    /// Needs to be recreated when the surface is resized, `sample_count` has to match the color target
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+
+        Self {
+            view: texture.create_default_view(),
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                lod_min_clamp: -100.0,
+                lod_max_clamp: 100.0,
+                compare: wgpu::CompareFunction::LessEqual,
+            }),
+            texture,
+            width,
+            height,
+        }
+    }
+
+    /// Clearing sets the depth to the far plane
+    pub fn depth_attachment(
+        &self,
+        load_op: wgpu::LoadOp,
+    ) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'_> {
+        wgpu::RenderPassDepthStencilAttachmentDescriptor {
+            attachment: &self.view,
+            depth_load_op: load_op,
+            depth_store_op: wgpu::StoreOp::Store,
+            clear_depth: 1.0,
+            stencil_load_op: wgpu::LoadOp::Load,
+            stencil_store_op: wgpu::StoreOp::Store,
+            clear_stencil: 0,
+        }
+    }
+
+    /// Nearest filtering is usually what you want for pixel art
+    pub fn set_filter(&mut self, device: &wgpu::Device, filter: wgpu::FilterMode) {
+        self.sampler = create_sampler(device, filter);
+    }
+}
+
+fn create_sampler(device: &wgpu::Device, filter: wgpu::FilterMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        lod_min_clamp: -100.0,
+        lod_max_clamp: 100.0,
+        compare: wgpu::CompareFunction::Always,
+    })
+}
@@ -0,0 +1,153 @@
+use super::{
+    renderer_api::RendererApi, sprite_sheet::SpriteSheet, sub_texture::SubTexture2D,
+    texture::Texture,
+};
+use anyhow::{anyhow, Context, Result};
+use image::RgbaImage;
+use std::{path::Path, rc::Rc};
+
+/// Packs many small images in a single texture at runtime
+///
+/// Images are placed on shelves from the tallest to the shortest. The border pixels of each image
+/// are repeated in the padding so filtering doesn't bleed the neighbours in.
+/// ```rust,ignore
+///     let mut packer = AtlasPacker::new();
+///     packer.add_file("assets/player.png")?;
+///     packer.add_file("assets/enemy.png")?;
+///     let atlas = packer.build(&mut app.renderer.api)?;
+///     let player = atlas.get("player").unwrap();
+/// ```
+pub struct AtlasPacker {
+    /// Pixels between images
+    pub padding: u32,
+    /// Largest width or height of the texture
+    pub max_size: u32,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl Default for AtlasPacker {
+    fn default() -> Self {
+        Self {
+            padding: 1,
+            max_size: 4096,
+            images: vec![],
+        }
+    }
+}
+
+impl AtlasPacker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.push((String::from(name), image));
+    }
+
+    /// The image is named after the file, without the extension
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let image =
+            image::open(path).with_context(|| format!("Failed to load image {:?}", path))?;
+        let name = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("Invalid image name {:?}", path))?;
+        self.add_image(name, image.to_rgba());
+        Ok(())
+    }
+
+    /// Errors when the images don't fit in `max_size`
+    pub fn build(&self, api: &mut RendererApi) -> Result<SpriteSheet> {
+        let padding = self.padding;
+        let mut order = (0..self.images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.images[i].1.height()));
+
+        let area = self
+            .images
+            .iter()
+            .map(|(_, image)| (image.width() + padding * 2) * (image.height() + padding * 2))
+            .sum::<u32>();
+        let widest = self
+            .images
+            .iter()
+            .map(|(_, image)| image.width() + padding * 2)
+            .max()
+            .unwrap_or(1);
+        let mut width = ((area as f32).sqrt() as u32)
+            .max(widest)
+            .next_power_of_two();
+
+        let (positions, height) = loop {
+            if width > self.max_size {
+                return Err(anyhow!(
+                    "{} images don't fit in a {}x{} atlas",
+                    self.images.len(),
+                    self.max_size,
+                    self.max_size
+                ));
+            }
+            let (positions, height) = self.pack(&order, width);
+            if height <= self.max_size {
+                break (positions, height);
+            }
+            width *= 2;
+        };
+
+        let mut atlas = RgbaImage::new(width, height.max(1));
+        for ((_, image), &(x, y)) in self.images.iter().zip(positions.iter()) {
+            if image.width() == 0 || image.height() == 0 {
+                continue;
+            }
+            // (x, y) is the top left of the image, the padding is around it
+            for ay in y - padding..y + image.height() + padding {
+                for ax in x - padding..x + image.width() + padding {
+                    let sx = clamp_offset(ax, x, image.width());
+                    let sy = clamp_offset(ay, y, image.height());
+                    atlas.put_pixel(ax, ay, *image.get_pixel(sx, sy));
+                }
+            }
+        }
+
+        let texture = Rc::new(Texture::from_rgba(
+            api,
+            atlas.width(),
+            atlas.height(),
+            &atlas,
+            Some("Texture atlas"),
+        ));
+        let mut sheet = SpriteSheet::new(texture.clone());
+        for ((name, image), &(x, y)) in self.images.iter().zip(positions.iter()) {
+            sheet.insert(
+                name,
+                SubTexture2D::from_pixels(texture.clone(), x, y, image.width(), image.height()),
+            );
+        }
+        Ok(sheet)
+    }
+
+    /// Position of each image, in the order they were added, and the height used
+    fn pack(&self, order: &[usize], width: u32) -> (Vec<(u32, u32)>, u32) {
+        let padding = self.padding;
+        let mut positions = vec![(0, 0); self.images.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for &i in order {
+            let image = &self.images[i].1;
+            let (w, h) = (image.width() + padding * 2, image.height() + padding * 2);
+            if x + w > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            positions[i] = (x + padding, y + padding);
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        (positions, y + shelf_height)
+    }
+}
+
+/// Pixel of the image to copy at `position` of the atlas, the edges are repeated in the padding
+fn clamp_offset(position: u32, start: u32, size: u32) -> u32 {
+    (position.max(start) - start).min(size.max(1) - 1)
+}
@@ -0,0 +1,780 @@
+//! Import of maps made with Tiled, https://www.mapeditor.org
+//!
+//! Orthogonal maps of a fixed size are supported, with inline or external tilesets, tile and
+//! object layers, groups and properties. Image layers are skipped.
+
+use super::{
+    renderer_api::RendererApi,
+    sub_texture::SubTexture2D,
+    texture::Texture,
+    tilemap::{
+        Layer, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, Tile,
+        TileAnimationFrame, TileData, TileLayer, Tilemap, Tileset,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use glam::{Vec2, Vec4};
+use roxmltree::Node;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+/// A tileset before its images are loaded
+struct TilesetInfo {
+    name: String,
+    tile_width: u32,
+    tile_height: u32,
+    tile_count: u32,
+    margin: u32,
+    spacing: u32,
+    image: Option<PathBuf>,
+    /// Tiles of image collection tilesets have their own image
+    tiles: Vec<(u32, Option<PathBuf>, TileData)>,
+    properties: Properties,
+}
+
+/// Visibility, opacity and offset are passed down to the layers of a group
+#[derive(Clone, Copy)]
+struct Group {
+    visible: bool,
+    opacity: f32,
+    offset: Vec2,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            opacity: 1.0,
+            offset: Vec2::zero(),
+        }
+    }
+}
+
+/// Images shared by many tilesets are only loaded once
+type Textures = HashMap<PathBuf, Rc<Texture>>;
+
+pub fn load_tmx(api: &mut RendererApi, path: &Path) -> Result<Tilemap> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read map {:?}", path))?;
+    let document =
+        roxmltree::Document::parse(&text).with_context(|| format!("Invalid map {:?}", path))?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        return Err(anyhow!("{:?} isn't a Tiled map", path));
+    }
+    check_map(
+        map.attribute("orientation").unwrap_or("orthogonal"),
+        map.attribute("infinite") == Some("1"),
+    )?;
+
+    let mut tilemap = Tilemap::new(
+        xml_u32(map, "width")?,
+        xml_u32(map, "height")?,
+        xml_u32(map, "tilewidth")?,
+        xml_u32(map, "tileheight")?,
+    );
+    tilemap.background_color = map
+        .attribute("backgroundcolor")
+        .map(parse_color)
+        .transpose()?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut textures = Textures::new();
+    for node in map.children().filter(Node::is_element) {
+        match node.tag_name().name() {
+            "properties" => tilemap.properties = xml_properties(node)?,
+            "tileset" => {
+                let first_gid = xml_u32(node, "firstgid")?;
+                let info = match node.attribute("source") {
+                    Some(source) => load_external_tileset(&directory.join(source))?,
+                    None => xml_tileset(node, directory)?,
+                };
+                tilemap
+                    .tilesets
+                    .push(build_tileset(api, first_gid, info, &mut textures)?);
+            }
+            _ => {}
+        }
+    }
+    xml_layers(map, Group::default(), &mut tilemap.layers)?;
+    Ok(tilemap)
+}
+
+pub fn load_tmj(api: &mut RendererApi, path: &Path) -> Result<Tilemap> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read map {:?}", path))?;
+    let map: Value =
+        serde_json::from_str(&text).with_context(|| format!("Invalid map {:?}", path))?;
+    check_map(
+        map["orientation"].as_str().unwrap_or("orthogonal"),
+        map["infinite"].as_bool().unwrap_or(false),
+    )?;
+
+    let mut tilemap = Tilemap::new(
+        json_u32(&map, "width")?,
+        json_u32(&map, "height")?,
+        json_u32(&map, "tilewidth")?,
+        json_u32(&map, "tileheight")?,
+    );
+    tilemap.background_color = map["backgroundcolor"]
+        .as_str()
+        .map(parse_color)
+        .transpose()?;
+    tilemap.properties = json_properties(&map)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut textures = Textures::new();
+    for tileset in json_array(&map, "tilesets") {
+        let first_gid = json_u32(tileset, "firstgid")?;
+        let info = match tileset["source"].as_str() {
+            Some(source) => load_external_tileset(&directory.join(source))?,
+            None => json_tileset(tileset, directory)?,
+        };
+        tilemap
+            .tilesets
+            .push(build_tileset(api, first_gid, info, &mut textures)?);
+    }
+    json_layers(&map, Group::default(), &mut tilemap.layers)?;
+    Ok(tilemap)
+}
+
+fn check_map(orientation: &str, infinite: bool) -> Result<()> {
+    if orientation != "orthogonal" {
+        return Err(anyhow!("{} maps aren't supported", orientation));
+    }
+    if infinite {
+        return Err(anyhow!("Infinite maps aren't supported"));
+    }
+    Ok(())
+}
+
+/// `.tsx` or `.tsj` file
+fn load_external_tileset(path: &Path) -> Result<TilesetInfo> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read tileset {:?}", path))?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tsx") | Some("xml") => {
+            let document = roxmltree::Document::parse(&text)
+                .with_context(|| format!("Invalid tileset {:?}", path))?;
+            xml_tileset(document.root_element(), directory)
+        }
+        _ => {
+            let tileset: Value = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid tileset {:?}", path))?;
+            json_tileset(&tileset, directory)
+        }
+    }
+}
+
+fn build_tileset(
+    api: &mut RendererApi,
+    first_gid: u32,
+    info: TilesetInfo,
+    textures: &mut Textures,
+) -> Result<Tileset> {
+    let mut tileset = match &info.image {
+        Some(image) => Tileset::from_image(
+            &info.name,
+            first_gid,
+            load_texture(api, image, textures)?,
+            info.tile_width,
+            info.tile_height,
+            info.margin,
+            info.spacing,
+        ),
+        None => Tileset::new(&info.name, first_gid, info.tile_width, info.tile_height),
+    };
+    tileset.tile_count = tileset.tile_count.max(info.tile_count);
+    tileset.properties = info.properties;
+    for (id, image, data) in info.tiles {
+        if let Some(image) = image {
+            let texture = load_texture(api, &image, textures)?;
+            if tileset.sprites.len() <= id as usize {
+                tileset.sprites.resize(id as usize + 1, None);
+            }
+            tileset.sprites[id as usize] = Some(SubTexture2D::from_texture(texture));
+            tileset.tile_count = tileset.tile_count.max(id + 1);
+        }
+        tileset.tiles.insert(id, data);
+    }
+    Ok(tileset)
+}
+
+fn load_texture(
+    api: &mut RendererApi,
+    path: &Path,
+    textures: &mut Textures,
+) -> Result<Rc<Texture>> {
+    if let Some(texture) = textures.get(path) {
+        return Ok(texture.clone());
+    }
+    let mut texture = Texture::from_file(api, path)?;
+    // linear filtering bleeds the neighbouring tiles in
+    texture.set_filter(&api.device, wgpu::FilterMode::Nearest);
+    let texture = Rc::new(texture);
+    textures.insert(path.to_path_buf(), texture.clone());
+    Ok(texture)
+}
+
+/// Raw gids of a layer, `encoding` and `compression` are the attributes of the layer data
+fn decode_tiles(encoding: Option<&str>, compression: Option<&str>, data: &str) -> Result<Vec<u32>> {
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(|gid| gid.trim())
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| {
+                gid.parse::<u32>()
+                    .with_context(|| format!("Invalid tile {}", gid))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = base64::decode(data.trim()).context("Invalid base64 tile data")?;
+            let mut decompressed = vec![];
+            let bytes = match compression {
+                None | Some("") => bytes,
+                Some("zlib") => {
+                    flate2::read::ZlibDecoder::new(&bytes[..])
+                        .read_to_end(&mut decompressed)
+                        .context("Invalid zlib tile data")?;
+                    decompressed
+                }
+                Some("gzip") => {
+                    flate2::read::GzDecoder::new(&bytes[..])
+                        .read_to_end(&mut decompressed)
+                        .context("Invalid gzip tile data")?;
+                    decompressed
+                }
+                Some(compression) => {
+                    return Err(anyhow!("{} compression isn't supported", compression))
+                }
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(encoding) => Err(anyhow!("{} encoding isn't supported", encoding)),
+        None => Err(anyhow!("Tile data has no encoding")),
+    }
+}
+
+fn tile_layer(
+    name: &str,
+    width: u32,
+    height: u32,
+    gids: Vec<u32>,
+    group: Group,
+) -> Result<TileLayer> {
+    let tiles = gids.into_iter().map(Tile::from_raw).collect();
+    let mut layer = TileLayer::new(name, width, height, tiles)?;
+    layer.visible = group.visible;
+    layer.opacity = group.opacity;
+    layer.offset = group.offset;
+    Ok(layer)
+}
+
+/// `#AARRGGBB` or `#RRGGBB`
+fn parse_color(color: &str) -> Result<Vec4> {
+    let hex = color.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).with_context(|| format!("Invalid color {}", color))?;
+    let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+    match hex.len() {
+        6 => Ok(Vec4::new(channel(16), channel(8), channel(0), 1.0)),
+        8 => Ok(Vec4::new(channel(16), channel(8), channel(0), channel(24))),
+        _ => Err(anyhow!("Invalid color {}", color)),
+    }
+}
+
+fn parse_property(name: &str, kind: &str, value: &str) -> Result<Option<PropertyValue>> {
+    let invalid = || format!("Invalid {} property {}", kind, name);
+    Ok(Some(match kind {
+        "bool" => PropertyValue::Bool(value == "true"),
+        "int" => PropertyValue::Int(value.parse().with_context(invalid)?),
+        "float" => PropertyValue::Float(value.parse().with_context(invalid)?),
+        "color" if value.is_empty() => PropertyValue::Color(Vec4::zero()),
+        "color" => PropertyValue::Color(parse_color(value)?),
+        "file" => PropertyValue::File(String::from(value)),
+        "object" => PropertyValue::Object(value.parse().with_context(invalid)?),
+        "" | "string" => PropertyValue::String(String::from(value)),
+        _ => {
+            log::warn!("Property {} of type {} isn't supported", name, kind);
+            return Ok(None);
+        }
+    }))
+}
+
+fn xml_attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name)
+        .ok_or_else(|| anyhow!("<{}> has no {} attribute", node.tag_name().name(), name))
+}
+
+fn xml_u32(node: Node, name: &str) -> Result<u32> {
+    let value = xml_attribute(node, name)?;
+    value
+        .parse()
+        .with_context(|| format!("Invalid {} {}", name, value))
+}
+
+fn xml_f32_or(node: Node, name: &str, default: f32) -> Result<f32> {
+    match node.attribute(name) {
+        Some(value) => value
+            .parse()
+            .with_context(|| format!("Invalid {} {}", name, value)),
+        None => Ok(default),
+    }
+}
+
+fn xml_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn xml_properties(node: Node) -> Result<Properties> {
+    let mut properties = Properties::new();
+    let node = match xml_child(node, "properties") {
+        Some(child) => child,
+        None if node.has_tag_name("properties") => node,
+        None => return Ok(properties),
+    };
+    for property in node
+        .children()
+        .filter(|child| child.has_tag_name("property"))
+    {
+        let name = xml_attribute(property, "name")?;
+        // multiline strings are in the text instead of the value
+        let value = property
+            .attribute("value")
+            .or_else(|| property.text())
+            .unwrap_or("");
+        let kind = property.attribute("type").unwrap_or("string");
+        if let Some(value) = parse_property(name, kind, value)? {
+            properties.insert(String::from(name), value);
+        }
+    }
+    Ok(properties)
+}
+
+fn xml_class(node: Node) -> String {
+    // renamed from type to class in Tiled 1.9
+    String::from(
+        node.attribute("class")
+            .or_else(|| node.attribute("type"))
+            .unwrap_or(""),
+    )
+}
+
+fn xml_tileset(node: Node, directory: &Path) -> Result<TilesetInfo> {
+    let mut tiles = vec![];
+    for tile in node.children().filter(|child| child.has_tag_name("tile")) {
+        let animation = match xml_child(tile, "animation") {
+            Some(animation) => animation
+                .children()
+                .filter(|child| child.has_tag_name("frame"))
+                .map(|frame| {
+                    Ok(TileAnimationFrame {
+                        tile_id: xml_u32(frame, "tileid")?,
+                        duration: Duration::from_millis(xml_u32(frame, "duration")? as u64),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![],
+        };
+        let image = match xml_child(tile, "image") {
+            Some(image) => Some(directory.join(xml_attribute(image, "source")?)),
+            None => None,
+        };
+        let data = TileData {
+            class: xml_class(tile),
+            properties: xml_properties(tile)?,
+            animation,
+        };
+        tiles.push((xml_u32(tile, "id")?, image, data));
+    }
+    let image = match xml_child(node, "image") {
+        Some(image) => Some(directory.join(xml_attribute(image, "source")?)),
+        None => None,
+    };
+    Ok(TilesetInfo {
+        name: String::from(node.attribute("name").unwrap_or("")),
+        tile_width: xml_u32(node, "tilewidth")?,
+        tile_height: xml_u32(node, "tileheight")?,
+        tile_count: xml_u32(node, "tilecount").unwrap_or(0),
+        margin: xml_u32(node, "margin").unwrap_or(0),
+        spacing: xml_u32(node, "spacing").unwrap_or(0),
+        image,
+        tiles,
+        properties: xml_properties(node)?,
+    })
+}
+
+/// Settings of a layer combined with the ones of its group
+fn xml_group(node: Node, parent: Group) -> Result<Group> {
+    Ok(Group {
+        visible: parent.visible && node.attribute("visible") != Some("0"),
+        opacity: parent.opacity * xml_f32_or(node, "opacity", 1.0)?,
+        offset: parent.offset
+            + Vec2::new(
+                xml_f32_or(node, "offsetx", 0.0)?,
+                xml_f32_or(node, "offsety", 0.0)?,
+            ),
+    })
+}
+
+fn xml_layers(node: Node, parent: Group, layers: &mut Vec<Layer>) -> Result<()> {
+    for child in node.children().filter(Node::is_element) {
+        let name = child.attribute("name").unwrap_or("");
+        let kind = child.tag_name().name();
+        if !["layer", "objectgroup", "group", "imagelayer"].contains(&kind) {
+            continue;
+        }
+        let group = xml_group(child, parent)?;
+        let tint = child.attribute("tintcolor").map(parse_color).transpose()?;
+        let mut layer = match kind {
+            "layer" => {
+                let data = xml_child(child, "data")
+                    .ok_or_else(|| anyhow!("Layer {} has no data", name))?;
+                let gids = match data.attribute("encoding") {
+                    // the oldest format, one element per tile
+                    None => data
+                        .children()
+                        .filter(|tile| tile.has_tag_name("tile"))
+                        .map(|tile| Ok(xml_u32(tile, "gid").unwrap_or(0)))
+                        .collect::<Result<Vec<_>>>()?,
+                    encoding => decode_tiles(
+                        encoding,
+                        data.attribute("compression"),
+                        data.text().unwrap_or(""),
+                    )?,
+                };
+                let width = xml_u32(child, "width")?;
+                let height = xml_u32(child, "height")?;
+                Layer::Tiles(tile_layer(name, width, height, gids, group)?)
+            }
+            "objectgroup" => {
+                let mut layer = ObjectLayer::new(name);
+                layer.visible = group.visible;
+                layer.opacity = group.opacity;
+                layer.offset = group.offset;
+                for object in child
+                    .children()
+                    .filter(|child| child.has_tag_name("object"))
+                {
+                    layer.objects.push(xml_object(object)?);
+                }
+                Layer::Objects(layer)
+            }
+            "group" => {
+                xml_layers(child, group, layers)?;
+                continue;
+            }
+            _ => {
+                log::warn!("Image layer {} isn't supported", name);
+                continue;
+            }
+        };
+        match &mut layer {
+            Layer::Tiles(layer) => {
+                layer.properties = xml_properties(child)?;
+                layer.tint = tint.unwrap_or_else(Vec4::one);
+            }
+            Layer::Objects(layer) => {
+                layer.properties = xml_properties(child)?;
+                layer.tint = tint.unwrap_or_else(Vec4::one);
+            }
+        }
+        layers.push(layer);
+    }
+    Ok(())
+}
+
+fn xml_points(node: Node) -> Result<Vec<Vec2>> {
+    xml_attribute(node, "points")?
+        .split_whitespace()
+        .map(|point| {
+            let mut coordinates = point.split(',').map(|coordinate| coordinate.parse::<f32>());
+            match (coordinates.next(), coordinates.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Ok(Vec2::new(x, y)),
+                _ => Err(anyhow!("Invalid point {}", point)),
+            }
+        })
+        .collect()
+}
+
+fn xml_object(node: Node) -> Result<MapObject> {
+    if node.attribute("template").is_some() {
+        log::warn!("Object templates aren't supported, only the overridden values are loaded");
+    }
+    let shape = if xml_child(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if xml_child(node, "point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = xml_child(node, "polygon") {
+        ObjectShape::Polygon(xml_points(polygon)?)
+    } else if let Some(polyline) = xml_child(node, "polyline") {
+        ObjectShape::Polyline(xml_points(polyline)?)
+    } else if let Some(text) = xml_child(node, "text") {
+        ObjectShape::Text(String::from(text.text().unwrap_or("")))
+    } else {
+        ObjectShape::Rectangle
+    };
+    Ok(MapObject {
+        id: xml_u32(node, "id").unwrap_or(0),
+        name: String::from(node.attribute("name").unwrap_or("")),
+        class: xml_class(node),
+        position: Vec2::new(xml_f32_or(node, "x", 0.0)?, xml_f32_or(node, "y", 0.0)?),
+        size: Vec2::new(
+            xml_f32_or(node, "width", 0.0)?,
+            xml_f32_or(node, "height", 0.0)?,
+        ),
+        rotation: xml_f32_or(node, "rotation", 0.0)?,
+        tile: xml_u32(node, "gid").ok().and_then(Tile::from_raw),
+        shape,
+        visible: node.attribute("visible") != Some("0"),
+        properties: xml_properties(node)?,
+    })
+}
+
+fn json_u32(value: &Value, key: &str) -> Result<u32> {
+    value[key]
+        .as_u64()
+        .map(|number| number as u32)
+        .ok_or_else(|| anyhow!("{} should be a positive number", key))
+}
+
+fn json_f32_or(value: &Value, key: &str, default: f32) -> f32 {
+    value[key].as_f64().map_or(default, |number| number as f32)
+}
+
+fn json_str<'a>(value: &'a Value, key: &str) -> &'a str {
+    value[key].as_str().unwrap_or("")
+}
+
+fn json_array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value[key].as_array().into_iter().flatten()
+}
+
+fn json_properties(value: &Value) -> Result<Properties> {
+    let mut properties = Properties::new();
+    for property in json_array(value, "properties") {
+        let name = json_str(property, "name");
+        let kind = property["type"].as_str().unwrap_or("string");
+        let value = match &property["value"] {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        if let Some(value) = parse_property(name, kind, &value)? {
+            properties.insert(String::from(name), value);
+        }
+    }
+    Ok(properties)
+}
+
+fn json_class(value: &Value) -> String {
+    String::from(
+        value["class"]
+            .as_str()
+            .or_else(|| value["type"].as_str())
+            .unwrap_or(""),
+    )
+}
+
+fn json_tileset(value: &Value, directory: &Path) -> Result<TilesetInfo> {
+    let mut tiles = vec![];
+    for tile in json_array(value, "tiles") {
+        let animation = json_array(tile, "animation")
+            .map(|frame| {
+                Ok(TileAnimationFrame {
+                    tile_id: json_u32(frame, "tileid")?,
+                    duration: Duration::from_millis(json_u32(frame, "duration")? as u64),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let data = TileData {
+            class: json_class(tile),
+            properties: json_properties(tile)?,
+            animation,
+        };
+        let image = tile["image"].as_str().map(|image| directory.join(image));
+        tiles.push((json_u32(tile, "id")?, image, data));
+    }
+    Ok(TilesetInfo {
+        name: String::from(json_str(value, "name")),
+        tile_width: json_u32(value, "tilewidth")?,
+        tile_height: json_u32(value, "tileheight")?,
+        tile_count: json_u32(value, "tilecount").unwrap_or(0),
+        margin: json_u32(value, "margin").unwrap_or(0),
+        spacing: json_u32(value, "spacing").unwrap_or(0),
+        image: value["image"].as_str().map(|image| directory.join(image)),
+        tiles,
+        properties: json_properties(value)?,
+    })
+}
+
+fn json_layers(value: &Value, parent: Group, layers: &mut Vec<Layer>) -> Result<()> {
+    for child in json_array(value, "layers") {
+        let name = json_str(child, "name");
+        let group = Group {
+            visible: parent.visible && child["visible"].as_bool().unwrap_or(true),
+            opacity: parent.opacity * json_f32_or(child, "opacity", 1.0),
+            offset: parent.offset
+                + Vec2::new(
+                    json_f32_or(child, "offsetx", 0.0),
+                    json_f32_or(child, "offsety", 0.0),
+                ),
+        };
+        let tint = child["tintcolor"]
+            .as_str()
+            .map(parse_color)
+            .transpose()?
+            .unwrap_or_else(Vec4::one);
+        let properties = json_properties(child)?;
+        match json_str(child, "type") {
+            "tilelayer" => {
+                let gids = match &child["data"] {
+                    Value::String(data) => decode_tiles(
+                        child["encoding"].as_str(),
+                        child["compression"].as_str(),
+                        data,
+                    )?,
+                    data => data
+                        .as_array()
+                        .ok_or_else(|| anyhow!("Layer {} has no data", name))?
+                        .iter()
+                        .map(|gid| gid.as_u64().unwrap_or(0) as u32)
+                        .collect(),
+                };
+                let width = json_u32(child, "width")?;
+                let height = json_u32(child, "height")?;
+                let mut layer = tile_layer(name, width, height, gids, group)?;
+                layer.tint = tint;
+                layer.properties = properties;
+                layers.push(Layer::Tiles(layer));
+            }
+            "objectgroup" => {
+                let mut layer = ObjectLayer::new(name);
+                layer.visible = group.visible;
+                layer.opacity = group.opacity;
+                layer.offset = group.offset;
+                layer.tint = tint;
+                layer.properties = properties;
+                for object in json_array(child, "objects") {
+                    layer.objects.push(json_object(object)?);
+                }
+                layers.push(Layer::Objects(layer));
+            }
+            "group" => json_layers(child, group, layers)?,
+            _ => log::warn!("Image layer {} isn't supported", name),
+        }
+    }
+    Ok(())
+}
+
+fn json_points(value: &Value) -> Vec<Vec2> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|point| Vec2::new(json_f32_or(point, "x", 0.0), json_f32_or(point, "y", 0.0)))
+        .collect()
+}
+
+fn json_object(value: &Value) -> Result<MapObject> {
+    if value["template"].is_string() {
+        log::warn!("Object templates aren't supported, only the overridden values are loaded");
+    }
+    let shape = if value["ellipse"].as_bool() == Some(true) {
+        ObjectShape::Ellipse
+    } else if value["point"].as_bool() == Some(true) {
+        ObjectShape::Point
+    } else if value["polygon"].is_array() {
+        ObjectShape::Polygon(json_points(&value["polygon"]))
+    } else if value["polyline"].is_array() {
+        ObjectShape::Polyline(json_points(&value["polyline"]))
+    } else if value["text"].is_object() {
+        ObjectShape::Text(String::from(json_str(&value["text"], "text")))
+    } else {
+        ObjectShape::Rectangle
+    };
+    Ok(MapObject {
+        id: json_u32(value, "id").unwrap_or(0),
+        name: String::from(json_str(value, "name")),
+        class: json_class(value),
+        position: Vec2::new(json_f32_or(value, "x", 0.0), json_f32_or(value, "y", 0.0)),
+        size: Vec2::new(
+            json_f32_or(value, "width", 0.0),
+            json_f32_or(value, "height", 0.0),
+        ),
+        rotation: json_f32_or(value, "rotation", 0.0),
+        tile: json_u32(value, "gid").ok().and_then(Tile::from_raw),
+        shape,
+        visible: value["visible"].as_bool().unwrap_or(true),
+        properties: json_properties(value)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn decode_csv_tiles() {
+        assert_eq!(
+            decode_tiles(Some("csv"), None, "\n1,2,0,\n2147483649,3\n").unwrap(),
+            vec![1, 2, 0, 0x8000_0001, 3]
+        );
+        assert!(decode_tiles(Some("csv"), None, "1,x").is_err());
+    }
+
+    #[test]
+    fn decode_base64_tiles() {
+        let gids = [1u32, 0, 0x4000_0002, 300];
+        let bytes = gids
+            .iter()
+            .flat_map(|gid| gid.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decode_tiles(
+                Some("base64"),
+                None,
+                &format!("\n  {}\n", base64::encode(&bytes))
+            )
+            .unwrap(),
+            gids.to_vec()
+        );
+
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        let compressed = base64::encode(&encoder.finish().unwrap());
+        assert_eq!(
+            decode_tiles(Some("base64"), Some("zlib"), &compressed).unwrap(),
+            gids.to_vec()
+        );
+        assert!(decode_tiles(Some("base64"), Some("zlib"), &base64::encode(&bytes)).is_err());
+        assert!(decode_tiles(Some("base64"), Some("zstd"), &compressed).is_err());
+    }
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(
+            parse_color("#ff8000").unwrap(),
+            Vec4::new(1.0, 128.0 / 255.0, 0.0, 1.0)
+        );
+        // the alpha comes first
+        assert_eq!(
+            parse_color("#80ff0000").unwrap(),
+            Vec4::new(1.0, 0.0, 0.0, 128.0 / 255.0)
+        );
+        assert_eq!(
+            parse_color("00ff00").unwrap(),
+            Vec4::new(0.0, 1.0, 0.0, 1.0)
+        );
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("#gg0000").is_err());
+    }
+}
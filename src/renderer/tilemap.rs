@@ -0,0 +1,925 @@
+use super::{
+    camera::Camera,
+    renderer_2d::{quad_transform, Renderer2D},
+    renderer_api::RendererApi,
+    sub_texture::SubTexture2D,
+    texture::Texture,
+    tiled,
+};
+use anyhow::{anyhow, Result};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use std::{collections::HashMap, path::Path, rc::Rc, time::Duration};
+
+/// Width and height of a chunk, in tiles
+const CHUNK_SIZE: u32 = 16;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Also used by hexagonal maps to rotate tiles, ignored since only orthogonal maps are supported
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Vec4),
+    /// Path relative to the file it was defined in
+    File(String),
+    /// Id of a `MapObject`
+    Object(u32),
+}
+
+impl PropertyValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PropertyValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Ints are converted
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Int(value) => Some(*value as f64),
+            PropertyValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::String(value) | PropertyValue::File(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/// A tile placed in a layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    /// Global id, the tileset is the one with the biggest `first_gid` not above it
+    pub gid: u32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Swaps x and y, applied before the other flips. Tiled rotates tiles with it
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    pub fn new(gid: u32) -> Self {
+        Self {
+            gid,
+            flip_horizontal: false,
+            flip_vertical: false,
+            flip_diagonal: false,
+        }
+    }
+
+    /// Gid with the flip flags in the highest bits, like Tiled stores them. 0 is an empty cell
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        let gid = raw
+            & !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+        if gid == 0 {
+            return None;
+        }
+        Some(Self {
+            gid,
+            flip_horizontal: raw & FLIPPED_HORIZONTALLY != 0,
+            flip_vertical: raw & FLIPPED_VERTICALLY != 0,
+            flip_diagonal: raw & FLIPPED_DIAGONALLY != 0,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileAnimationFrame {
+    /// Id of the tile in its tileset
+    pub tile_id: u32,
+    pub duration: Duration,
+}
+
+/// What Tiled knows about a tile of a tileset
+#[derive(Clone, Debug, Default)]
+pub struct TileData {
+    pub class: String,
+    pub properties: Properties,
+    /// Empty when the tile isn't animated
+    pub animation: Vec<TileAnimationFrame>,
+}
+
+pub struct Tileset {
+    pub name: String,
+    /// Global id of the first tile
+    pub first_gid: u32,
+    pub tile_count: u32,
+    /// In pixels, can be bigger than the tiles of the map
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Indexed by the id of the tile in the tileset
+    pub sprites: Vec<Option<SubTexture2D>>,
+    /// Only tiles with data are in there
+    pub tiles: HashMap<u32, TileData>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    pub fn new(name: &str, first_gid: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            name: String::from(name),
+            first_gid,
+            tile_count: 0,
+            tile_width,
+            tile_height,
+            sprites: vec![],
+            tiles: HashMap::new(),
+            properties: Properties::new(),
+        }
+    }
+
+    /// Tiles cut from a single image, from left to right and top to bottom.
+    /// `margin` is around the image and `spacing` between the tiles, in pixels
+    pub fn from_image(
+        name: &str,
+        first_gid: u32,
+        texture: Rc<Texture>,
+        tile_width: u32,
+        tile_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let mut tileset = Self::new(name, first_gid, tile_width, tile_height);
+        let columns =
+            (texture.width.saturating_sub(margin * 2) + spacing) / (tile_width + spacing).max(1);
+        let rows =
+            (texture.height.saturating_sub(margin * 2) + spacing) / (tile_height + spacing).max(1);
+        for row in 0..rows {
+            for column in 0..columns {
+                tileset.sprites.push(Some(SubTexture2D::from_pixels(
+                    texture.clone(),
+                    margin + column * (tile_width + spacing),
+                    margin + row * (tile_height + spacing),
+                    tile_width,
+                    tile_height,
+                )));
+            }
+        }
+        tileset.tile_count = tileset.sprites.len() as u32;
+        tileset
+    }
+
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    pub fn sprite(&self, tile_id: u32) -> Option<&SubTexture2D> {
+        self.sprites
+            .get(tile_id as usize)
+            .and_then(|sprite| sprite.as_ref())
+    }
+
+    /// Id of the tile showing at `time` in the animation of `tile_id`
+    pub fn animated_tile_id(&self, tile_id: u32, time: Duration) -> u32 {
+        let animation = match self.tiles.get(&tile_id) {
+            Some(data) if !data.animation.is_empty() => &data.animation,
+            _ => return tile_id,
+        };
+        let total = animation
+            .iter()
+            .map(|frame| frame.duration.as_millis())
+            .sum::<u128>();
+        if total == 0 {
+            return tile_id;
+        }
+        let mut time = time.as_millis() % total;
+        for frame in animation {
+            let duration = frame.duration.as_millis();
+            if time < duration {
+                return frame.tile_id;
+            }
+            time -= duration;
+        }
+        tile_id
+    }
+}
+
+/// A grid of tiles, split in chunks so the tiles out of view are skipped quickly
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Multiplies the color of every tile
+    pub tint: Vec4,
+    /// In pixels, y goes down like in Tiled
+    pub offset: Vec2,
+    pub properties: Properties,
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Tile>>,
+    /// Index of the tiles that aren't empty, per chunk from left to right and top to bottom
+    chunks: Vec<Vec<u32>>,
+}
+
+impl TileLayer {
+    /// `tiles` go from left to right and top to bottom
+    pub fn new(name: &str, width: u32, height: u32, tiles: Vec<Option<Tile>>) -> Result<Self> {
+        if tiles.len() != (width * height) as usize {
+            return Err(anyhow!(
+                "Layer {} has {} tiles instead of {}",
+                name,
+                tiles.len(),
+                width * height
+            ));
+        }
+        let mut layer = Self {
+            name: String::from(name),
+            visible: true,
+            opacity: 1.0,
+            tint: Vec4::one(),
+            offset: Vec2::zero(),
+            properties: Properties::new(),
+            width,
+            height,
+            tiles,
+            chunks: vec![],
+        };
+        layer.build_chunks();
+        Ok(layer)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// `None` for empty cells and cells outside of the layer
+    pub fn tile(&self, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    /// Cells outside of the layer are ignored
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<Tile>) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let index = y * self.width + x;
+        let was_empty = self.tiles[index as usize].is_none();
+        self.tiles[index as usize] = tile;
+        let chunk = &mut self.chunks[self.chunk_index(x, y)];
+        match (was_empty, tile.is_none()) {
+            (true, false) => {
+                let position = chunk.binary_search(&index).unwrap_or_else(|i| i);
+                chunk.insert(position, index);
+            }
+            (false, true) => chunk.retain(|&i| i != index),
+            _ => {}
+        }
+    }
+
+    fn chunk_columns(&self) -> u32 {
+        (self.width + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+
+    fn chunk_index(&self, x: u32, y: u32) -> usize {
+        ((y / CHUNK_SIZE) * self.chunk_columns() + x / CHUNK_SIZE) as usize
+    }
+
+    fn build_chunks(&mut self) {
+        let rows = (self.height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        self.chunks = vec![vec![]; (self.chunk_columns() * rows) as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                if self.tiles[index as usize].is_some() {
+                    let chunk = self.chunk_index(x, y);
+                    self.chunks[chunk].push(index);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the position of the object, in pixels
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+    Text(String),
+}
+
+/// Anything placed freely on the map, like spawn points or trigger zones
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    /// In pixels from the top left of the map, the bottom left of tile objects
+    pub position: Vec2,
+    /// In pixels
+    pub size: Vec2,
+    /// In degrees, clockwise
+    pub rotation: f32,
+    /// Objects showing a tile are drawn with their layer
+    pub tile: Option<Tile>,
+    pub shape: ObjectShape,
+    pub visible: bool,
+    pub properties: Properties,
+}
+
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub tint: Vec4,
+    pub offset: Vec2,
+    pub properties: Properties,
+    pub objects: Vec<MapObject>,
+}
+
+impl ObjectLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            visible: true,
+            opacity: 1.0,
+            tint: Vec4::one(),
+            offset: Vec2::zero(),
+            properties: Properties::new(),
+            objects: vec![],
+        }
+    }
+}
+
+/// Group layers of Tiled are flattened, their children take their place
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        match self {
+            Layer::Tiles(layer) => &layer.name,
+            Layer::Objects(layer) => &layer.name,
+        }
+    }
+
+    pub fn properties(&self) -> &Properties {
+        match self {
+            Layer::Tiles(layer) => &layer.properties,
+            Layer::Objects(layer) => &layer.properties,
+        }
+    }
+}
+
+/// An orthogonal map of tiles drawn with the 2D renderer
+///
+/// The top left of the map is at `origin` and each tile is `tile_size` world units, tiles from
+/// tilesets bigger than the map tiles are scaled the same way and grow up and right like in
+/// Tiled. Only the chunks seen by the camera are drawn.
+///
+/// A tile is solid when it, or its layer, has a `collision` property set to true. Collision
+/// layers can be hidden, hidden layers still count.
+/// ```rust,ignore
+///     let mut map = Tilemap::from_file(&mut app.renderer.api, "assets/maps/level_1.tmx")?;
+///     // in on_update
+///     map.update(app.delta_t);
+///     let grounded = map.overlaps_solid(feet_min, feet_max);
+///     // in on_render
+///     map.draw(&mut app.renderer_2d, &app.camera_controller.camera);
+/// ```
+pub struct Tilemap {
+    /// In tiles
+    pub width: u32,
+    pub height: u32,
+    /// In pixels
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tilesets: Vec<Tileset>,
+    /// From the bottom to the top
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+    pub background_color: Option<Vec4>,
+    /// World position of the top left of the map
+    pub origin: Vec3,
+    /// World size of a tile
+    pub tile_size: Vec2,
+    /// Added to the z of each layer, layers share the z of the origin by default and are drawn
+    /// in order
+    pub layer_spacing: f32,
+    /// Drives the animated tiles
+    time: Duration,
+}
+
+impl Tilemap {
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            tilesets: vec![],
+            layers: vec![],
+            properties: Properties::new(),
+            background_color: None,
+            origin: Vec3::zero(),
+            tile_size: Vec2::one(),
+            layer_spacing: 0.0,
+            time: Duration::default(),
+        }
+    }
+
+    /// Loads a Tiled map saved as `.tmx` or `.tmj`, with its tilesets and their images
+    pub fn from_file<P: AsRef<Path>>(api: &mut RendererApi, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tmx") | Some("xml") => tiled::load_tmx(api, path),
+            Some("tmj") | Some("json") => tiled::load_tmj(api, path),
+            _ => Err(anyhow!("Unknown map format {:?}", path)),
+        }
+    }
+
+    /// Moves the animated tiles forward
+    pub fn update(&mut self, delta_t: Duration) {
+        self.time += delta_t;
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find_map(|layer| match layer {
+            Layer::Tiles(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    pub fn tile_layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find_map(|layer| match layer {
+            Layer::Tiles(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        self.layers.iter().find_map(|layer| match layer {
+            Layer::Objects(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    /// Every object of every object layer
+    pub fn objects(&self) -> impl Iterator<Item = &MapObject> {
+        self.layers
+            .iter()
+            .filter_map(|layer| match layer {
+                Layer::Objects(layer) => Some(layer.objects.iter()),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    pub fn object(&self, name: &str) -> Option<&MapObject> {
+        self.objects().find(|object| object.name == name)
+    }
+
+    pub fn tileset_of(&self, gid: u32) -> Option<&Tileset> {
+        self.tilesets.iter().find(|tileset| tileset.contains(gid))
+    }
+
+    pub fn tile_data(&self, tile: Tile) -> Option<&TileData> {
+        self.tileset_of(tile.gid)
+            .and_then(|tileset| tileset.tiles.get(&(tile.gid - tileset.first_gid)))
+    }
+
+    /// Column and row of the tile under a world position, `None` outside of the map
+    pub fn world_to_tile(&self, position: Vec2) -> Option<(u32, u32)> {
+        let local = (position - self.origin.truncate()) / self.tile_size;
+        let (x, y) = (local.x().floor(), (-local.y()).floor());
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+
+    /// World position of the center of a tile
+    pub fn tile_to_world(&self, x: u32, y: u32) -> Vec2 {
+        let (min, max) = self.tile_bounds(x, y);
+        (min + max) / 2.0
+    }
+
+    /// World position of the bottom left and top right corners of a tile
+    pub fn tile_bounds(&self, x: u32, y: u32) -> (Vec2, Vec2) {
+        let top_left = self.origin.truncate() + Vec2::new(x as f32, -(y as f32)) * self.tile_size;
+        (
+            top_left - Vec2::new(0.0, self.tile_size.y()),
+            top_left + Vec2::new(self.tile_size.x(), 0.0),
+        )
+    }
+
+    /// Converts a position in pixels from the top left of the map, like the position of objects
+    pub fn pixel_to_world(&self, position: Vec2) -> Vec2 {
+        let tiles = position / Vec2::new(self.tile_width as f32, self.tile_height as f32);
+        self.origin.truncate() + Vec2::new(tiles.x(), -tiles.y()) * self.tile_size
+    }
+
+    /// Tile of a layer under a world position
+    pub fn tile_at(&self, layer: &str, position: Vec2) -> Option<Tile> {
+        let (x, y) = self.world_to_tile(position)?;
+        self.tile_layer(layer)?.tile(x, y)
+    }
+
+    pub fn is_solid(&self, x: u32, y: u32) -> bool {
+        self.layers.iter().any(|layer| match layer {
+            Layer::Tiles(layer) => match layer.tile(x, y) {
+                Some(tile) => {
+                    is_collision(&layer.properties)
+                        || self
+                            .tile_data(tile)
+                            .map_or(false, |data| is_collision(&data.properties))
+                }
+                None => false,
+            },
+            _ => false,
+        })
+    }
+
+    pub fn is_solid_at(&self, position: Vec2) -> bool {
+        self.world_to_tile(position)
+            .map_or(false, |(x, y)| self.is_solid(x, y))
+    }
+
+    /// Solid tiles touching a world rectangle, to resolve collisions against
+    pub fn solid_tiles_in(&self, min: Vec2, max: Vec2) -> Vec<(u32, u32)> {
+        let (x0, y0, x1, y1) = match self.tile_range(min, max) {
+            Some(range) => range,
+            None => return vec![],
+        };
+        let mut tiles = vec![];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if self.is_solid(x, y) {
+                    tiles.push((x, y));
+                }
+            }
+        }
+        tiles
+    }
+
+    pub fn overlaps_solid(&self, min: Vec2, max: Vec2) -> bool {
+        !self.solid_tiles_in(min, max).is_empty()
+    }
+
+    /// Draws the visible layers seen by the camera, tile objects included
+    pub fn draw(&self, renderer_2d: &mut Renderer2D, camera: &dyn Camera) {
+        for (index, layer) in self.layers.iter().enumerate() {
+            let z = self.origin.z() + self.layer_spacing * index as f32;
+            match layer {
+                Layer::Tiles(layer) if layer.visible => {
+                    self.draw_layer(renderer_2d, camera, layer, z)
+                }
+                Layer::Objects(layer) if layer.visible => {
+                    let tint = layer.tint * Vec4::new(1.0, 1.0, 1.0, layer.opacity);
+                    for object in layer.objects.iter().filter(|object| object.visible) {
+                        if let Some(tile) = object.tile {
+                            self.draw_object(renderer_2d, layer.offset, object, tile, z, tint);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_layer(
+        &self,
+        renderer_2d: &mut Renderer2D,
+        camera: &dyn Camera,
+        layer: &TileLayer,
+        z: f32,
+    ) {
+        let offset = self.pixel_to_world(layer.offset) - self.origin.truncate();
+        // tiles bigger than the map tiles stick out of their cell up and right
+        let overhang = self
+            .tilesets
+            .iter()
+            .map(|tileset| {
+                Vec2::new(
+                    tileset.tile_width as f32 / self.tile_width as f32,
+                    tileset.tile_height as f32 / self.tile_height as f32,
+                )
+            })
+            .fold(Vec2::one(), |a, b| a.max(b))
+            - Vec2::one();
+        let (chunk_x0, chunk_y0, chunk_x1, chunk_y1) = match visible_rect(camera, z) {
+            Some((min, max)) => {
+                let min = min - offset - overhang * self.tile_size;
+                let max = max - offset;
+                match self.tile_range(min, max) {
+                    Some((x0, y0, x1, y1)) => (
+                        x0 / CHUNK_SIZE,
+                        y0 / CHUNK_SIZE,
+                        (x1 + CHUNK_SIZE - 1) / CHUNK_SIZE,
+                        (y1 + CHUNK_SIZE - 1) / CHUNK_SIZE,
+                    ),
+                    None => return,
+                }
+            }
+            // the camera doesn't look at the map straight, everything might be in view
+            None => (
+                0,
+                0,
+                layer.chunk_columns(),
+                (layer.height + CHUNK_SIZE - 1) / CHUNK_SIZE,
+            ),
+        };
+
+        let tint = layer.tint * Vec4::new(1.0, 1.0, 1.0, layer.opacity);
+        for chunk_y in chunk_y0..chunk_y1 {
+            for chunk_x in chunk_x0..chunk_x1.min(layer.chunk_columns()) {
+                let chunk = (chunk_y * layer.chunk_columns() + chunk_x) as usize;
+                for &index in layer.chunks.get(chunk).into_iter().flatten() {
+                    let tile = match layer.tiles[index as usize] {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let (x, y) = (index % layer.width, index / layer.width);
+                    let (bottom_left, _) = self.tile_bounds(x, y);
+                    self.draw_tile(
+                        renderer_2d,
+                        tile,
+                        (bottom_left + offset).extend(z),
+                        0.0,
+                        None,
+                        tint,
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_object(
+        &self,
+        renderer_2d: &mut Renderer2D,
+        layer_offset: Vec2,
+        object: &MapObject,
+        tile: Tile,
+        z: f32,
+        tint: Vec4,
+    ) {
+        let bottom_left = self.pixel_to_world(object.position + layer_offset);
+        let size = object.size / Vec2::new(self.tile_width as f32, self.tile_height as f32)
+            * self.tile_size;
+        self.draw_tile(
+            renderer_2d,
+            tile,
+            bottom_left.extend(z),
+            -object.rotation.to_radians(),
+            Some(size),
+            tint,
+        );
+    }
+
+    /// `rotation` is around the bottom left corner, the size defaults to the size of the tileset
+    /// tiles
+    fn draw_tile(
+        &self,
+        renderer_2d: &mut Renderer2D,
+        tile: Tile,
+        bottom_left: Vec3,
+        rotation: f32,
+        size: Option<Vec2>,
+        tint: Vec4,
+    ) {
+        let tileset = match self.tileset_of(tile.gid) {
+            Some(tileset) => tileset,
+            None => return,
+        };
+        let tile_id = tileset.animated_tile_id(tile.gid - tileset.first_gid, self.time);
+        let sprite = match tileset.sprite(tile_id) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+        let size = size.unwrap_or_else(|| {
+            Vec2::new(
+                tileset.tile_width as f32 / self.tile_width as f32,
+                tileset.tile_height as f32 / self.tile_height as f32,
+            ) * self.tile_size
+        });
+        let transform =
+            Mat4::from_rotation_translation(Quat::from_rotation_z(rotation), bottom_left)
+                * quad_transform((size / 2.0).extend(0.0), size, 0.0);
+        renderer_2d.draw_quad_with_transform(
+            transform,
+            flipped_tex_coords(sprite.tex_coords(), tile),
+            &sprite.texture,
+            tint,
+        );
+    }
+
+    /// Columns and rows touching a world rectangle, the ends are excluded
+    fn tile_range(&self, min: Vec2, max: Vec2) -> Option<(u32, u32, u32, u32)> {
+        let local_min = (min - self.origin.truncate()) / self.tile_size;
+        let local_max = (max - self.origin.truncate()) / self.tile_size;
+        // rows go down
+        let x0 = local_min.x().floor().max(0.0);
+        let x1 = local_max.x().ceil().min(self.width as f32);
+        let y0 = (-local_max.y()).floor().max(0.0);
+        let y1 = (-local_min.y()).ceil().min(self.height as f32);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        Some((x0 as u32, y0 as u32, x1 as u32, y1 as u32))
+    }
+}
+
+fn is_collision(properties: &Properties) -> bool {
+    properties
+        .get("collision")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Texture coordinates of a sprite for the bottom left, bottom right, top right and top left
+/// corners, with the flips of the tile. `tex_coords` come from `SubTexture2D::tex_coords`
+fn flipped_tex_coords(tex_coords: [Vec2; 4], tile: Tile) -> [[f32; 2]; 4] {
+    let mut flipped = [[0.0; 2]; 4];
+    for (corner, tex_coord) in flipped.iter_mut().enumerate() {
+        // each flip is its own inverse, so the corner showing at `corner` is found by flipping
+        // it back in the reverse order
+        let mut source = corner;
+        if tile.flip_vertical {
+            source = 3 - source;
+        }
+        if tile.flip_horizontal {
+            source ^= 1;
+        }
+        if tile.flip_diagonal {
+            // swaps the bottom left and top right, the corners on the diagonal stay
+            source = match source {
+                0 => 2,
+                2 => 0,
+                other => other,
+            };
+        }
+        *tex_coord = [tex_coords[source].x(), tex_coords[source].y()];
+    }
+    flipped
+}
+
+/// World rectangle seen by the camera on the plane at `z`.
+/// `None` when the camera doesn't see the whole plane straight on
+fn visible_rect(camera: &dyn Camera, z: f32) -> Option<(Vec2, Vec2)> {
+    let inverse = camera.view_projection_matrix().inverse();
+    let unproject = |x: f32, y: f32, depth: f32| {
+        let point = inverse * Vec4::new(x, y, depth, 1.0);
+        point.truncate() / point.w()
+    };
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+        let near = unproject(x, y, 0.0);
+        let direction = unproject(x, y, 1.0) - near;
+        if direction.z().abs() < std::f32::EPSILON {
+            return None;
+        }
+        let t = (z - near.z()) / direction.z();
+        if !(0.0..=1.0).contains(&t) {
+            return None;
+        }
+        let point = (near + direction * t).truncate();
+        min = min.min(point);
+        max = max.max(point);
+    }
+    Some((min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_from_raw() {
+        assert_eq!(Tile::from_raw(0), None);
+        // flags without a gid are still an empty cell
+        assert_eq!(
+            Tile::from_raw(FLIPPED_HORIZONTALLY | FLIPPED_DIAGONALLY),
+            None
+        );
+        assert_eq!(Tile::from_raw(7), Some(Tile::new(7)));
+        assert_eq!(
+            Tile::from_raw(FLIPPED_HORIZONTALLY | 5),
+            Some(Tile {
+                flip_horizontal: true,
+                ..Tile::new(5)
+            })
+        );
+        assert_eq!(
+            Tile::from_raw(FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | 3),
+            Some(Tile {
+                flip_vertical: true,
+                flip_diagonal: true,
+                ..Tile::new(3)
+            })
+        );
+        assert_eq!(Tile::from_raw(ROTATED_HEXAGONAL | 2), Some(Tile::new(2)));
+    }
+
+    #[test]
+    fn flipped_tex_coords_follow_tiled() {
+        let (bottom_left, bottom_right, top_right, top_left) =
+            ([0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]);
+        let tex_coords = [
+            Vec2::from(bottom_left),
+            Vec2::from(bottom_right),
+            Vec2::from(top_right),
+            Vec2::from(top_left),
+        ];
+        let flipped = |flip_horizontal, flip_vertical, flip_diagonal| {
+            let tile = Tile {
+                flip_horizontal,
+                flip_vertical,
+                flip_diagonal,
+                ..Tile::new(1)
+            };
+            flipped_tex_coords(tex_coords, tile)
+        };
+
+        assert_eq!(
+            flipped(false, false, false),
+            [bottom_left, bottom_right, top_right, top_left]
+        );
+        assert_eq!(
+            flipped(true, false, false),
+            [bottom_right, bottom_left, top_left, top_right]
+        );
+        assert_eq!(
+            flipped(false, true, false),
+            [top_left, top_right, bottom_right, bottom_left]
+        );
+        assert_eq!(
+            flipped(false, false, true),
+            [top_right, bottom_right, bottom_left, top_left]
+        );
+        // Tiled rotates 90 degrees clockwise with a horizontal and a diagonal flip
+        assert_eq!(
+            flipped(true, false, true),
+            [bottom_right, top_right, top_left, bottom_left]
+        );
+        assert_eq!(
+            flipped(true, true, false),
+            [top_right, top_left, bottom_left, bottom_right]
+        );
+    }
+
+    #[test]
+    fn world_to_tile_at_the_edges() {
+        // 4 by 3 tiles, the top left at the origin and rows going down
+        let map = Tilemap::new(4, 3, 16, 16);
+        assert_eq!(map.world_to_tile(Vec2::new(0.0, 0.0)), Some((0, 0)));
+        assert_eq!(map.world_to_tile(Vec2::new(0.5, -0.5)), Some((0, 0)));
+        assert_eq!(map.world_to_tile(Vec2::new(3.99, -2.99)), Some((3, 2)));
+        assert_eq!(map.world_to_tile(Vec2::new(-0.01, -1.0)), None);
+        assert_eq!(map.world_to_tile(Vec2::new(1.0, 0.01)), None);
+        assert_eq!(map.world_to_tile(Vec2::new(4.0, -1.0)), None);
+        assert_eq!(map.world_to_tile(Vec2::new(1.0, -3.0)), None);
+    }
+
+    #[test]
+    fn tile_range_is_clamped_to_the_map() {
+        let map = Tilemap::new(4, 3, 16, 16);
+        assert_eq!(
+            map.tile_range(Vec2::new(-10.0, -10.0), Vec2::new(10.0, 10.0)),
+            Some((0, 0, 4, 3))
+        );
+        let (min, max) = map.tile_bounds(1, 1);
+        assert_eq!(map.tile_range(min, max), Some((1, 1, 2, 2)));
+        // rectangles touching the map from outside don't touch any tile
+        assert_eq!(
+            map.tile_range(Vec2::new(4.0, -1.0), Vec2::new(5.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            map.tile_range(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)),
+            None
+        );
+        assert_eq!(
+            map.tile_range(Vec2::new(5.0, -1.0), Vec2::new(6.0, 0.0)),
+            None
+        );
+    }
+}